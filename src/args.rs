@@ -1,12 +1,596 @@
-use std::env;
+use std::{env, fs, path::PathBuf, process, str::FromStr, time::Duration};
 
+use crate::audio::{MissingSound, Player};
+use crate::clock::OnSuspend;
+use crate::config::{self, ConfigValue};
+use crate::milestone::{Milestone, MilestoneAlert};
+use crate::{
+    clock,
+    mic::MicProbe,
+    output::{Icon, OutputFormat, OutputStyle, TextTemplate},
+    status::Privacy,
+    tasks::TaskSource,
+    theme::Theme,
+};
 
-pub fn handle_args() -> Option<String> {
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300); // Window for dropping repeats
+const DEFAULT_RATE_LIMIT: usize = 5; // Maximum number of commands handled per second
+const DEFAULT_DISTRACTION_LIMIT: Duration = Duration::from_secs(60); // Focus time before an alert
+const ENV_PREFIX: &str = "WAYBAR_POMODORO_"; // `--rate-limit` reads `WAYBAR_POMODORO_RATE_LIMIT`
+
+/// How an option consumes its value.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Switch, // Takes no value
+    Value,  // Takes one value; the last occurrence wins
+    List,   // May be repeated; `;` separates entries in the environment
+}
+
+/// Every option, so that each can also be set through the environment.
+const OPTIONS: &[(&str, Kind)] = &[
+    ("--work", Kind::Value),
+    ("--short-break", Kind::Value),
+    ("--long-break", Kind::Value),
+    ("--long-break-every", Kind::Value),
+    ("--normalize", Kind::Switch),
+    ("--audio-player", Kind::Value),
+    ("--sound-work", Kind::Value),
+    ("--sound-break", Kind::Value),
+    ("--sound-long-break", Kind::Value),
+    ("--missing-sound", Kind::Value),
+    ("--no-sound", Kind::Switch),
+    ("--debounce-ms", Kind::Value),
+    ("--rate-limit", Kind::Value),
+    ("--auto-start", Kind::List),
+    ("--namespace", Kind::Value),
+    ("--auto-task", Kind::Switch),
+    ("--focus-sample", Kind::Value),
+    ("--distraction", Kind::List),
+    ("--distraction-after", Kind::Value),
+    ("--theme", Kind::Value),
+    ("--output-style", Kind::Value),
+    ("--format", Kind::Value),
+    ("--format-text", Kind::Value),
+    ("--icon", Kind::List),
+    ("--no-icons", Kind::Switch),
+    ("--speak-at", Kind::List),
+    ("--warmup", Kind::Value),
+    ("--grace", Kind::Value),
+    ("--metrics-textfile", Kind::Value),
+    ("--quiet-fullscreen", Kind::Switch),
+    ("--mic-probe", Kind::Value),
+    ("--rollover-cap", Kind::Value),
+    ("--status-file", Kind::Switch),
+    ("--signal", Kind::Value),
+    ("--status-fd", Kind::Value),
+    ("--inbox", Kind::Value),
+    ("--suggest-task", Kind::Value),
+    ("--pause-reminder", Kind::Value),
+    ("--milestone", Kind::List),
+    ("--milestone-alert", Kind::Value),
+    ("--break-countdown", Kind::Switch),
+    ("--phase-actions", Kind::Switch),
+    ("--auto-start-breaks", Kind::Switch),
+    ("--auto-start-work", Kind::Switch),
+    ("--overtime", Kind::Switch),
+    ("--privacy", Kind::Value),
+    ("--on-suspend", Kind::Value),
+    ("--idle-pause", Kind::Value),
+    ("--daily-goal", Kind::Value),
+    ("--headless", Kind::Switch),
+];
+
+/// Options with their values, in the order they are applied.
+type Options = Vec<(&'static str, Option<String>)>;
+
+/// What the binary was asked to do.
+pub enum Command {
+    /// Run the timer, printing its state for waybar.
+    Run,
+    /// Play a sound file once and exit.
+    SoundPreview(String),
+    /// Print the saved timer state and the configuration in effect.
+    Dump,
+    /// Send a command to the running timer.
+    Ctl(String),
+    /// Print click actions and key bindings for the commands.
+    Bindings,
+    /// Act on a `pomodoro://` link.
+    Uri(String),
+    /// Write the desktop entries, plus the autostart files with `autostart`, for a daemon run
+    /// with `daemon_args`.
+    Install {
+        autostart: bool,
+        daemon_args: Vec<String>,
+    },
+}
+
+pub struct Args {
+    pub command: Command,
+    pub sound_file: Option<String>,
+    pub sound_work: Option<String>, // Sound for the start of a work session
+    pub sound_break: Option<String>, // Sound for the start of a break
+    pub sound_long_break: Option<String>, // Sound for the start of a long break
+    pub work: Option<Duration>,     // Overrides the 25 minute work session
+    pub short_break: Option<Duration>, // Overrides the 5 minute short break
+    pub long_break: Option<Duration>, // Overrides the 30 minute long break
+    pub long_break_every: Option<u64>, // Overrides the 4 pomodoros before each long break
+    pub normalize: bool,            // Peak-normalize sound files before playing them
+    pub player: Player,             // Program that plays sounds
+    pub missing_sound: MissingSound, // What to do about sound files that do not exist
+    pub no_sound: bool,             // Play nothing, not even the bundled chime
+    pub debounce: Duration,         // Per-command debounce window
+    pub rate_limit: usize,          // Global limit of commands per second
+    pub auto_start: Vec<String>,    // Recurring start times such as `Mon-Fri 09:05`
+    pub namespace: Option<String>,  // Keeps separate state per project; `auto` detects it
+    pub auto_task: bool,            // Label sessions after the focused window
+    pub focus_sample: Option<Duration>, // Time between focused-window samples during work
+    pub distractions: Vec<String>,  // App ids or title fragments to alert about during work
+    pub distraction_after: Duration, // Time on a distraction before the alert
+    pub theme: Theme,               // Pango styling preset for the bar text
+    pub output_style: OutputStyle,  // Compact or spelled-out bar text
+    pub format: OutputFormat,       // Which bar the status lines are written for
+    pub format_text: Option<TextTemplate>, // Template for the bar text
+    pub icons: Vec<Icon>,           // Symbols chosen for states instead of the defaults
+    pub no_icons: bool,             // Leave the icon out of the bar text
+    pub speak_at: Vec<Duration>,    // Remaining times to announce aloud
+    pub warmup: Duration,           // Countdown before a fresh work session
+    pub grace: Duration,            // Window after a break in which auto-start holds off
+    pub metrics_textfile: Option<String>, // Where to write metrics for node_exporter
+    pub quiet_fullscreen: bool,     // Hold back alerts while a fullscreen window has focus
+    pub mic_probe: Option<MicProbe>, // Detects calls, during which chimes are muted
+    pub rollover_cap: Duration,     // Most unused short-break time added to a long break
+    pub status_file: bool,          // Mirror the status into a file under XDG_RUNTIME_DIR
+    pub signal: Option<i32>,        // Send the bar SIGRTMIN plus this on every change
+    pub status_fd: Option<i32>,     // Inherited descriptor to write the status to instead of stdout
+    pub inbox: Option<PathBuf>,     // File that `capture` appends to, e.g. a todo.txt
+    pub task_source: Option<TaskSource>, // Where to find a task for unlabeled sessions
+    pub pause_reminder: Duration,   // How long a paused work session waits for a first reminder
+    pub milestones: Vec<Milestone>, // Points in a work session to announce
+    pub milestone_alert: MilestoneAlert, // Whether milestones notify, chime or both
+    pub break_countdown: bool,      // Keep a notification with the time left in a break
+    pub phase_actions: bool,        // Offer Start, Snooze and Skip when a phase ends
+    pub auto_start_breaks: bool,    // Start a break as soon as the work session runs out
+    pub auto_start_work: bool,      // Start a work session as soon as the break runs out
+    pub overtime: bool,             // Count up past the end of a work session until `skip`
+    pub privacy: Privacy,           // Whether task labels stay out of replies and logs
+    pub on_suspend: OnSuspend,      // Whether time spent suspended counts or pauses the timer
+    pub idle_pause: Option<Duration>, // Idle time after which a work session pauses itself
+    pub daily_goal: Option<u64>,    // Work sessions to complete each day
+    pub headless: bool,             // Log notifications, sounds and signals instead
+}
+
+/// Parses the command line, layered over `WAYBAR_POMODORO_*` environment variables and the
+/// config file.
+pub fn handle_args() -> Args {
     let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <sound_file>", args[0]);
-        return None;
+    let program = &args[0];
+    let mut parsed = Args {
+        command: Command::Run,
+        sound_file: None,
+        sound_work: None,
+        sound_break: None,
+        sound_long_break: None,
+        work: None,
+        short_break: None,
+        long_break: None,
+        long_break_every: None,
+        normalize: false,
+        player: Player::default(),
+        missing_sound: MissingSound::default(),
+        no_sound: false,
+        debounce: DEFAULT_DEBOUNCE,
+        rate_limit: DEFAULT_RATE_LIMIT,
+        auto_start: Vec::new(),
+        namespace: None,
+        auto_task: false,
+        focus_sample: None,
+        distractions: Vec::new(),
+        distraction_after: DEFAULT_DISTRACTION_LIMIT,
+        theme: Theme::default(),
+        output_style: OutputStyle::default(),
+        format: OutputFormat::default(),
+        format_text: None,
+        icons: Vec::new(),
+        no_icons: false,
+        speak_at: Vec::new(),
+        warmup: Duration::ZERO,
+        grace: Duration::ZERO,
+        metrics_textfile: None,
+        quiet_fullscreen: false,
+        mic_probe: None,
+        rollover_cap: Duration::ZERO,
+        status_file: false,
+        signal: None,
+        status_fd: None,
+        inbox: None,
+        task_source: None,
+        pause_reminder: Duration::ZERO,
+        milestones: Vec::new(),
+        milestone_alert: MilestoneAlert::default(),
+        break_countdown: false,
+        phase_actions: false,
+        auto_start_breaks: false,
+        auto_start_work: false,
+        overtime: false,
+        privacy: Privacy::default(),
+        on_suspend: OnSuspend::default(),
+        idle_pause: None,
+        daily_goal: None,
+        headless: false,
+    };
+
+    let mut positional = Vec::new();
+    let mut cli_options = Vec::new();
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        // Everything after `ctl` is the command, flags like `start --in` included.
+        if positional.is_empty() && arg == "ctl" {
+            let command: Vec<&str> = rest.by_ref().map(String::as_str).collect();
+            if command.is_empty() {
+                usage(program);
+                process::exit(2);
+            }
+            parsed.command = Command::Ctl(command.join(" "));
+            break;
+        }
+        if arg == "--autostart" && positional.first() == Some(&"install") {
+            positional.push(arg.as_str());
+            continue;
+        }
+        match OPTIONS.iter().find(|(flag, _)| flag == arg) {
+            Some((flag, Kind::Switch)) => cli_options.push((*flag, None)),
+            Some((flag, _)) => cli_options.push((*flag, rest.next().cloned())),
+            None if arg.starts_with("--") => {
+                eprintln!("Unknown option {}", arg);
+                usage(program);
+                process::exit(2);
+            }
+            None => positional.push(arg.as_str()),
+        }
+    }
+
+    let (config_sound, config_options) =
+        config::load()
+            .and_then(config_options)
+            .unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                process::exit(2);
+            });
+
+    // Each layer replaces the lists of the layers below instead of extending them.
+    let mut options = Vec::new();
+    for layer in [config_options, env_options(), cli_options] {
+        options.retain(|(flag, _)| !layer.iter().any(|(newer, _)| newer == flag));
+        options.extend(layer);
+    }
+    for (flag, value) in options {
+        set_option(&mut parsed, program, flag, value);
     }
 
-    Some(args[1].clone())
+    match positional.as_slice() {
+        _ if matches!(parsed.command, Command::Ctl(_)) => {}
+        ["sound", "preview", file] => parsed.command = Command::SoundPreview(file.to_string()),
+        ["dump"] => parsed.command = Command::Dump,
+        ["bindings"] => parsed.command = Command::Bindings,
+        ["uri", uri] => parsed.command = Command::Uri(uri.to_string()),
+        ["install", tail @ ..] => {
+            let autostart = tail.contains(&"--autostart");
+            let sound_file = tail.iter().find(|arg| **arg != "--autostart");
+            parsed.sound_file = sound_file.map(|file| file.to_string()).or(config_sound);
+            parsed.command = Command::Install {
+                autostart,
+                daemon_args: daemon_args(&args, sound_file.copied()),
+            };
+        }
+        [sound_file, ..] => parsed.sound_file = Some(sound_file.to_string()),
+        [] => parsed.sound_file = config_sound,
+    }
+
+    if let Err(err) = validate(&parsed) {
+        eprintln!("{}", err);
+        process::exit(2);
+    }
+    parsed
+}
+
+/// The arguments to start the daemon with from an `install` command line: the options as given,
+/// and the sound file with an absolute path, as the daemon will not run from here.
+fn daemon_args(args: &[String], sound_file: Option<&str>) -> Vec<String> {
+    let mut daemon_args: Vec<String> = args[1..]
+        .iter()
+        .take_while(|arg| *arg != "install")
+        .cloned()
+        .collect();
+    if let Some(sound_file) = sound_file {
+        let path = fs::canonicalize(sound_file).unwrap_or_else(|_| sound_file.into());
+        daemon_args.push(path.to_string_lossy().into_owned());
+    }
+    daemon_args
+}
+
+/// Refuses values that would leave the timer silently broken.
+fn validate(parsed: &Args) -> Result<(), String> {
+    if let Some(signal) = parsed.signal {
+        let highest = libc::SIGRTMAX() - libc::SIGRTMIN();
+        if !(1..=highest).contains(&signal) {
+            return Err(format!("--signal must be between 1 and {}", highest));
+        }
+    }
+    if parsed.long_break_every == Some(0) {
+        return Err("--long-break-every must be at least 1".to_string());
+    }
+    if parsed.daily_goal == Some(0) {
+        return Err("--daily-goal must be at least 1".to_string());
+    }
+    if parsed.rate_limit == 0 {
+        return Err("--rate-limit must be at least 1, or every command is dropped".to_string());
+    }
+    Ok(())
+}
+
+/// Turns the settings from the config file, whose keys are the option names without dashes,
+/// e.g. `short-break = "10m"` or `short_break = "10m"`, into options. `sound` names the sound
+/// file.
+fn config_options(
+    settings: Vec<(String, ConfigValue)>,
+) -> Result<(Option<String>, Options), String> {
+    let mut sound = None;
+    let mut options = Vec::new();
+    for (key, value) in settings {
+        if key == "sound" {
+            match value {
+                ConfigValue::Scalar(path) => sound = Some(path),
+                _ => return Err("Setting sound must be a path".to_string()),
+            }
+            continue;
+        }
+        let Some((flag, kind)) = OPTIONS.iter().find(|(flag, _)| flag[2..] == key) else {
+            return Err(format!("Unknown setting {} in the config file", key));
+        };
+        match (kind, value) {
+            (Kind::Switch, ConfigValue::Bool(true)) => options.push((*flag, None)),
+            (Kind::Switch, ConfigValue::Bool(false)) => {}
+            (Kind::Switch, _) => return Err(format!("Setting {} must be true or false", key)),
+            (Kind::Value | Kind::List, ConfigValue::Scalar(value)) => {
+                options.push((*flag, Some(value)))
+            }
+            (Kind::List, ConfigValue::List(items)) => {
+                options.extend(items.into_iter().map(|item| (*flag, Some(item))))
+            }
+            _ => return Err(format!("Setting {} has the wrong type", key)),
+        }
+    }
+    Ok((sound, options))
+}
+
+fn env_options() -> Options {
+    let mut options = Vec::new();
+    for (flag, kind) in OPTIONS {
+        let name = format!(
+            "{}{}",
+            ENV_PREFIX,
+            flag[2..].to_uppercase().replace('-', "_")
+        );
+        let Ok(value) = env::var(&name) else {
+            continue;
+        };
+        match kind {
+            Kind::Switch if ["1", "true", "yes"].contains(&value.to_lowercase().as_str()) => {
+                options.push((*flag, None))
+            }
+            Kind::Switch => {}
+            Kind::Value => options.push((*flag, Some(value))),
+            Kind::List => options.extend(
+                value
+                    .split(';')
+                    .filter(|entry| !entry.trim().is_empty())
+                    .map(|entry| (*flag, Some(entry.trim().to_string()))),
+            ),
+        }
+    }
+    options
+}
+
+fn set_option(parsed: &mut Args, program: &str, flag: &str, value: Option<String>) {
+    match flag {
+        "--work" => parsed.work = Some(parse_duration(program, flag, value)),
+        "--short-break" => parsed.short_break = Some(parse_duration(program, flag, value)),
+        "--long-break" => parsed.long_break = Some(parse_duration(program, flag, value)),
+        "--long-break-every" => parsed.long_break_every = Some(parse_value(program, flag, value)),
+        "--normalize" => parsed.normalize = true,
+        "--audio-player" => parsed.player = parse_value(program, flag, value),
+        "--sound-work" => parsed.sound_work = Some(parse_value(program, flag, value)),
+        "--sound-break" => parsed.sound_break = Some(parse_value(program, flag, value)),
+        "--sound-long-break" => parsed.sound_long_break = Some(parse_value(program, flag, value)),
+        "--missing-sound" => parsed.missing_sound = parse_value(program, flag, value),
+        "--no-sound" => parsed.no_sound = true,
+        "--debounce-ms" => {
+            parsed.debounce = Duration::from_millis(parse_value(program, flag, value))
+        }
+        "--rate-limit" => parsed.rate_limit = parse_value(program, flag, value),
+        "--auto-start" => parsed.auto_start.push(parse_value(program, flag, value)),
+        "--namespace" => parsed.namespace = Some(parse_value(program, flag, value)),
+        "--auto-task" => parsed.auto_task = true,
+        "--focus-sample" => parsed.focus_sample = Some(parse_duration(program, flag, value)),
+        "--distraction" => parsed.distractions.push(parse_value(program, flag, value)),
+        "--distraction-after" => parsed.distraction_after = parse_duration(program, flag, value),
+        "--theme" => parsed.theme = parse_value(program, flag, value),
+        "--output-style" => parsed.output_style = parse_value(program, flag, value),
+        "--format" => parsed.format = parse_value(program, flag, value),
+        "--format-text" => parsed.format_text = Some(parse_value(program, flag, value)),
+        "--icon" => parsed.icons.push(parse_value(program, flag, value)),
+        "--no-icons" => parsed.no_icons = true,
+        "--speak-at" => {
+            let minutes: u64 = parse_value(program, flag, value);
+            let Some(seconds) = minutes.checked_mul(60) else {
+                eprintln!("Invalid or missing value for {}", flag);
+                usage(program);
+                process::exit(2);
+            };
+            parsed.speak_at.push(Duration::from_secs(seconds))
+        }
+        "--warmup" => parsed.warmup = parse_duration(program, flag, value),
+        "--grace" => parsed.grace = parse_duration(program, flag, value),
+        "--metrics-textfile" => parsed.metrics_textfile = Some(parse_value(program, flag, value)),
+        "--quiet-fullscreen" => parsed.quiet_fullscreen = true,
+        "--mic-probe" => parsed.mic_probe = Some(parse_value(program, flag, value)),
+        "--rollover-cap" => parsed.rollover_cap = parse_duration(program, flag, value),
+        "--status-file" => parsed.status_file = true,
+        "--signal" => parsed.signal = Some(parse_value(program, flag, value)),
+        "--status-fd" => parsed.status_fd = Some(parse_value(program, flag, value)),
+        "--inbox" => parsed.inbox = Some(parse_value(program, flag, value)),
+        "--suggest-task" => parsed.task_source = Some(parse_value(program, flag, value)),
+        "--pause-reminder" => parsed.pause_reminder = parse_duration(program, flag, value),
+        "--milestone" => parsed.milestones.push(parse_value(program, flag, value)),
+        "--milestone-alert" => parsed.milestone_alert = parse_value(program, flag, value),
+        "--break-countdown" => parsed.break_countdown = true,
+        "--phase-actions" => parsed.phase_actions = true,
+        "--auto-start-breaks" => parsed.auto_start_breaks = true,
+        "--auto-start-work" => parsed.auto_start_work = true,
+        "--overtime" => parsed.overtime = true,
+        "--privacy" => parsed.privacy = parse_value(program, flag, value),
+        "--on-suspend" => parsed.on_suspend = parse_value(program, flag, value),
+        "--idle-pause" => parsed.idle_pause = Some(parse_duration(program, flag, value)),
+        "--daily-goal" => parsed.daily_goal = Some(parse_value(program, flag, value)),
+        "--headless" => parsed.headless = true,
+        _ => unreachable!("option {} is not handled", flag),
+    }
+}
+
+fn parse_value<T: FromStr>(program: &str, flag: &str, value: Option<String>) -> T {
+    match value.map(|value| value.parse()) {
+        Some(Ok(value)) => value,
+        _ => {
+            eprintln!("Invalid or missing value for {}", flag);
+            usage(program);
+            process::exit(2);
+        }
+    }
+}
+
+/// Parses durations like `90`, `30s` or `10m`; bare numbers are seconds.
+fn parse_duration(program: &str, flag: &str, value: Option<String>) -> Duration {
+    match value.as_deref().and_then(clock::parse_duration) {
+        Some(duration) => duration,
+        None => {
+            eprintln!("Invalid or missing duration for {}", flag);
+            usage(program);
+            process::exit(2);
+        }
+    }
+}
+
+fn usage(program: &str) {
+    eprintln!("Usage: {} [options] [<sound_file>]", program);
+    eprintln!("       {} sound preview [--normalize] <file>", program);
+    eprintln!("       {} [options] dump", program);
+    eprintln!("       {} [--namespace <name|auto>] ctl <command>", program);
+    eprintln!("       {} [--namespace <name|auto>] bindings", program);
+    eprintln!(
+        "       {} [--namespace <name|auto>] uri <pomodoro://...>",
+        program
+    );
+    eprintln!(
+        "       {} [options] install [--autostart] [<sound_file>]",
+        program
+    );
+    eprintln!(
+        "
+Options:
+  --work <duration>             Length of a work session (default 25m)
+  --short-break <duration>      Length of a short break (default 5m)
+  --long-break <duration>       Length of a long break (default 30m)
+  --long-break-every <n>        Pomodoros before each long break (default 4)
+  --normalize                   Peak-normalize sounds before playing them
+  --audio-player <player>       auto, aplay, pw-play or paplay
+  --sound-work <file>           Sound for the start of a work session
+  --sound-break <file>          Sound for the start of a break
+  --sound-long-break <file>     Sound for the start of a long break
+  --missing-sound <behavior>    error, warn, chime or off when a sound file does not exist
+  --no-sound                    Play no sounds, not even the bundled chime
+  --debounce-ms <ms>            Drop repeats of a command within this window
+  --rate-limit <per_sec>        Handle at most this many commands per second
+  --auto-start <days HH:MM>     Start a session on a schedule (repeatable)
+  --namespace <name|auto>       Keep separate state for a project
+  --auto-task                   Label sessions after the focused window
+  --focus-sample <duration>     Sample the focused app during work
+  --distraction <app|title>     Alert when this keeps focus during work (repeatable)
+  --distraction-after <duration>
+                                How long a distraction may keep focus
+  --theme <name>                none, classic, colorblind or monochrome
+  --output-style <style>        standard or accessible
+  --format <bar>                waybar, polybar, i3blocks or plain (default waybar)
+  --format-text <template>      Bar text with placeholders, e.g. \"{{icon}} {{remaining}}\"
+  --icon <state>=<symbol>       Icon for work, short-break, paused etc. (repeatable)
+  --no-icons                    Leave the state icon out of the bar text
+  --speak-at <minutes>          Announce the remaining time aloud (repeatable)
+  --warmup <duration>           Count down before each work session starts
+  --grace <duration>            Hold off auto-start this long after a break
+  --metrics-textfile <path>     Write metrics for node_exporter every minute
+  --quiet-fullscreen            Hold back sounds and notifications over fullscreen windows
+  --mic-probe <probe>           pipewire or proc; mute chimes while the mic is in use
+  --rollover-cap <duration>     Roll unused short-break time into the long break
+  --status-file                 Mirror the status into $XDG_RUNTIME_DIR/waybar-pomodoro
+  --signal <n>                  Send the bar SIGRTMIN+n whenever the status changes
+  --status-fd <n>               Write the status to this inherited descriptor, not stdout
+  --inbox <file>                Where `capture` writes thoughts down
+  --suggest-task <source>       taskwarrior or a todo.txt file to suggest tasks from
+  --pause-reminder <duration>   Remind about a paused session, then at doubling intervals
+  --milestone <milestone>       Announce half, last-5m or last-1m of a session (repeatable)
+  --milestone-alert <alert>     notify, sound or both (default notify)
+  --break-countdown             Keep a notification with the time left in a break
+  --phase-actions               Offer Start, Snooze and Skip when a phase ends
+  --auto-start-breaks           Start each break when the work session runs out
+  --auto-start-work             Start each work session when the break runs out
+  --overtime                    Count up past the end of a work session until skip
+  --privacy <setting>           off or redact-tasks to keep tasks out of replies and logs
+  --on-suspend <policy>         continue counting through a suspend, or pause (default continue)
+  --idle-pause <duration>       Pause work when the screen locks or after this long idle
+  --daily-goal <pomodoros>      Show progress towards this many pomodoros a day
+  --headless                    Log notifications, sounds and signals instead of sending them"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(text: &str) -> Result<(Option<String>, Options), String> {
+        config_options(config::parse(text)?)
+    }
+
+    #[test]
+    fn settings_become_options() {
+        let (sound, options) = settings(
+            "short_break = \"10m\"\nno-icons = true\nnormalize = false\n\
+             distraction = [\"firefox\", \"YouTube\"]\nsound = \"~/ding.ogg\"",
+        )
+        .unwrap();
+        assert_eq!(sound.as_deref(), Some("~/ding.ogg"));
+        assert_eq!(
+            options,
+            [
+                ("--short-break", Some("10m".to_string())),
+                ("--no-icons", None),
+                ("--distraction", Some("firefox".to_string())),
+                ("--distraction", Some("YouTube".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        assert_eq!(
+            settings("short-brake = \"10m\"").unwrap_err(),
+            "Unknown setting short-brake in the config file"
+        );
+    }
+
+    #[test]
+    fn settings_of_the_wrong_type_are_rejected() {
+        assert!(settings("no-icons = \"yes\"").is_err());
+        assert!(settings("short-break = [\"10m\"]").is_err());
+        assert!(settings("sound = true").is_err());
+    }
 }
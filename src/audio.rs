@@ -0,0 +1,215 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    str::FromStr,
+};
+
+use crate::pomodoro::scratch_file;
+
+const TARGET_PEAK: f64 = 0.89; // Roughly -1 dBFS
+const PULSE_STREAM_PROPS: &str = "media.role=event";
+const PIPEWIRE_STREAM_PROPS: &str = "{ media.role = Notification }";
+const CHIME: &[u8] = include_bytes!("../assets/ding.wav");
+
+/// What to do about a sound file that does not exist.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MissingSound {
+    #[default]
+    Error, // Refuse to start
+    Warn,  // Warn once at startup and go on as if it was not given
+    Chime, // Warn once at startup and play the bundled chime in its place
+    Off,   // Go on as if it was not given, without a warning
+}
+
+impl FromStr for MissingSound {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "error" => Ok(MissingSound::Error),
+            "warn" => Ok(MissingSound::Warn),
+            "chime" => Ok(MissingSound::Chime),
+            "off" => Ok(MissingSound::Off),
+            _ => Err(format!("Unknown missing sound behavior {}", name)),
+        }
+    }
+}
+
+/// The program that plays sounds.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Player {
+    #[default]
+    Auto, // The first of aplay, pw-play and paplay that is installed
+    Aplay,  // ALSA, also through the pulse/pipewire plugins
+    PwPlay, // PipeWire
+    Paplay, // PulseAudio, or pipewire-pulse
+}
+
+impl FromStr for Player {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "auto" => Ok(Player::Auto),
+            "aplay" => Ok(Player::Aplay),
+            "pw-play" => Ok(Player::PwPlay),
+            "paplay" => Ok(Player::Paplay),
+            _ => Err(format!("Unknown audio player {}", name)),
+        }
+    }
+}
+
+impl Player {
+    fn run(self, path: &Path) -> io::Result<Output> {
+        match self {
+            Player::Auto => {
+                for player in [Player::Aplay, Player::PwPlay, Player::Paplay] {
+                    match player.run(path) {
+                        Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                        result => return result,
+                    }
+                }
+                Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "none of aplay, pw-play or paplay is installed",
+                ))
+            }
+            // aplay goes through the ALSA pulse/pipewire plugins, which pick the stream
+            // role up from the environment so the sound server can route or duck it.
+            Player::Aplay => Command::new("aplay")
+                .arg("-q")
+                .arg(path)
+                .env("PULSE_PROP", PULSE_STREAM_PROPS)
+                .env("PIPEWIRE_PROPS", PIPEWIRE_STREAM_PROPS)
+                .output(),
+            Player::PwPlay => Command::new("pw-play")
+                .args(["--properties", PIPEWIRE_STREAM_PROPS])
+                .arg(path)
+                .output(),
+            Player::Paplay => Command::new("paplay")
+                .arg(format!("--property={}", PULSE_STREAM_PROPS))
+                .arg(path)
+                .output(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Player::Auto => "an audio player",
+            Player::Aplay => "aplay",
+            Player::PwPlay => "pw-play",
+            Player::Paplay => "paplay",
+        }
+    }
+}
+
+/// Plays a sound file with `player`, optionally peak-normalizing it first into a copy kept
+/// apart for each `namespace`.
+pub fn play(
+    player: Player,
+    sound_file: &str,
+    normalize: bool,
+    namespace: Option<&str>,
+) -> Result<(), String> {
+    let path = if normalize {
+        normalized_copy(Path::new(sound_file), namespace).unwrap_or_else(|err| {
+            eprintln!("Not normalizing {}: {}", sound_file, err);
+            PathBuf::from(sound_file)
+        })
+    } else {
+        PathBuf::from(sound_file)
+    };
+
+    let output = player
+        .run(&path)
+        .map_err(|err| format!("Failed to run {}: {}", player.name(), err))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Failed to play sound: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+}
+
+/// Writes the chime built into the binary to a file the players can open.
+pub fn bundled_chime() -> Result<PathBuf, String> {
+    let target = scratch_file("chime.wav")?;
+    fs::write(&target, CHIME).map_err(|err| format!("{}: {}", target.display(), err))?;
+    Ok(target)
+}
+
+/// Writes a copy of a 16-bit PCM WAV file scaled so its peak sits at `TARGET_PEAK`.
+fn normalized_copy(sound_file: &Path, namespace: Option<&str>) -> Result<PathBuf, String> {
+    let mut bytes = fs::read(sound_file).map_err(|err| err.to_string())?;
+    let (start, len) = pcm16_samples(&bytes)?;
+
+    let samples = &mut bytes[start..start + len];
+    let peak = samples
+        .chunks_exact(2)
+        .map(|s| i16::from_le_bytes([s[0], s[1]]).unsigned_abs())
+        .max()
+        .unwrap_or(0);
+    if peak == 0 {
+        return Err("file is silent".to_string());
+    }
+
+    let gain = TARGET_PEAK * f64::from(i16::MAX) / f64::from(peak);
+    for sample in samples.chunks_exact_mut(2) {
+        let value = f64::from(i16::from_le_bytes([sample[0], sample[1]])) * gain;
+        let scaled = value.clamp(f64::from(i16::MIN), f64::from(i16::MAX)) as i16;
+        sample.copy_from_slice(&scaled.to_le_bytes());
+    }
+
+    let name = sound_file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "sound.wav".to_string());
+    let target = match namespace {
+        Some(namespace) => scratch_file(&format!("normalized.{}.{}", namespace, name))?,
+        None => scratch_file(&format!("normalized.{}", name))?,
+    };
+    fs::write(&target, bytes).map_err(|err| err.to_string())?;
+    Ok(target)
+}
+
+/// Returns the offset and length of the sample data in a 16-bit PCM WAV file.
+fn pcm16_samples(bytes: &[u8]) -> Result<(usize, usize), String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a WAV file".to_string());
+    }
+
+    let mut offset = 12;
+    let mut is_pcm16 = false;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = u32::from_le_bytes([
+            bytes[offset + 4],
+            bytes[offset + 5],
+            bytes[offset + 6],
+            bytes[offset + 7],
+        ]) as usize;
+        let body = offset + 8;
+        match id {
+            b"fmt " if size >= 16 && body + 16 <= bytes.len() => {
+                let format = u16::from_le_bytes([bytes[body], bytes[body + 1]]);
+                let bits = u16::from_le_bytes([bytes[body + 14], bytes[body + 15]]);
+                is_pcm16 = format == 1 && bits == 16;
+            }
+            b"data" => {
+                if !is_pcm16 {
+                    return Err("only 16-bit PCM WAV files can be normalized".to_string());
+                }
+                let len = size.min(bytes.len() - body) & !1;
+                return Ok((body, len));
+            }
+            _ => {}
+        }
+        offset = body + size + (size & 1);
+    }
+
+    Err("no data chunk found".to_string())
+}
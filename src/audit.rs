@@ -0,0 +1,93 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{clock, errors::Failure, pomodoro::state_file};
+
+/// Why the timer changed state.
+#[derive(Clone, Copy, Debug)]
+pub enum Cause {
+    Command,      // A command sent over the socket
+    AutoAdvance,  // A phase ran out and the next one was set up
+    AutoStart,    // An `--auto-start` rule fired
+    Schedule,     // A deferred `start --in/--at` fired
+    Warmup,       // The countdown before a work session ran out
+    Notification, // An action picked on a notification, e.g. *Cancel*
+    DayRollover,  // A new day began while the session was still paused
+    Suspend,      // The machine woke up from a suspend
+    Idle,         // The session went idle or came back
+}
+
+impl Cause {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Cause::Command => "command",
+            Cause::AutoAdvance => "auto-advance",
+            Cause::AutoStart => "auto-start",
+            Cause::Schedule => "schedule",
+            Cause::Warmup => "warmup",
+            Cause::Notification => "notification",
+            Cause::DayRollover => "day-rollover",
+            Cause::Suspend => "suspend",
+            Cause::Idle => "idle",
+        }
+    }
+}
+
+/// Append-only log of state transitions, one line per transition.
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(namespace: Option<&str>) -> Self {
+        let path = match namespace {
+            Some(namespace) => state_file(&format!("audit.{}.log", namespace)),
+            None => state_file("audit.log"),
+        };
+        Self { path }
+    }
+
+    #[cfg(test)]
+    pub fn at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a line such as `2026-10-14 09:05:00 +0200 command work paused -> work running`.
+    pub fn record(&self, cause: Cause, from: &str, to: &str) -> Result<(), Failure> {
+        let line = format!(
+            "{} {} {} -> {}\n",
+            clock::local_timestamp(),
+            cause.as_str(),
+            from,
+            to
+        );
+        self.path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)
+            })
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|err| Failure::new("audit", format!("{}: {}", self.path.display(), err)))
+    }
+
+    /// Returns the last `count` transitions, oldest first.
+    pub fn last(&self, count: usize) -> String {
+        let Ok(log) = fs::read_to_string(&self.path) else {
+            return "No transitions recorded".to_string();
+        };
+        let lines: Vec<&str> = log.lines().collect();
+        lines[lines.len().saturating_sub(count)..].join("\n")
+    }
+}
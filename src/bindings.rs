@@ -0,0 +1,70 @@
+use crate::control::{Usage, COMMANDS};
+
+/// Mouse actions on the waybar module, with the command each runs.
+const CLICKS: &[(&str, &str)] = &[
+    ("on-click", "toggle"),
+    ("on-click-right", "skip"),
+    ("on-click-middle", "reset"),
+    ("on-scroll-up", "add 1m"),
+    ("on-scroll-down", "sub 1m"),
+];
+
+/// Suggested keys for the commands used most; the other actions get commented-out lines.
+const KEYS: &[(&str, &str)] = &[("toggle", "p"), ("skip", "Shift+p"), ("done", "Ctrl+p")];
+
+/// Prints waybar click actions and sway and Hyprland key bindings for every command that makes
+/// sense on a click or a key, with `ctl` as the command line that reaches the timer.
+pub fn print(ctl: &str) {
+    let known = |command: &str| {
+        let name = command.split_whitespace().next().unwrap_or_default();
+        COMMANDS.iter().any(|(known, _)| *known == name)
+    };
+    let bindable: Vec<String> = COMMANDS
+        .iter()
+        .filter_map(|(name, usage)| match usage {
+            Usage::Action => Some(name.to_string()),
+            Usage::Adjust => Some(format!("{} 5m", name)),
+            _ => None,
+        })
+        .collect();
+
+    println!("// waybar: add to the \"custom/pomodoro\" module");
+    for (event, command) in CLICKS.iter().filter(|(_, command)| known(command)) {
+        println!("\"{}\": \"{} {}\",", event, ctl, command);
+    }
+
+    println!();
+    println!("# sway: add to ~/.config/sway/config");
+    for command in &bindable {
+        match key(command) {
+            Some(key) => println!("bindsym $mod+{} exec {} {}", key, ctl, command),
+            None => println!("# bindsym $mod+<key> exec {} {}", ctl, command),
+        }
+    }
+
+    println!();
+    println!("# Hyprland: add to ~/.config/hypr/hyprland.conf");
+    for command in &bindable {
+        match key(command) {
+            Some(key) => {
+                let (modifiers, key) = key.rsplit_once('+').unwrap_or(("", key));
+                let modifiers = format!("SUPER {}", modifiers.replace('+', " ").to_uppercase());
+                let key = key.to_uppercase();
+                println!(
+                    "bind = {}, {}, exec, {} {}",
+                    modifiers.trim(),
+                    key,
+                    ctl,
+                    command
+                );
+            }
+            None => println!("# bind = SUPER, <key>, exec, {} {}", ctl, command),
+        }
+    }
+}
+
+fn key(command: &str) -> Option<&'static str> {
+    KEYS.iter()
+        .find(|(bound, _)| *bound == command)
+        .map(|(_, key)| *key)
+}
@@ -0,0 +1,283 @@
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+const MIN_SUSPEND: Duration = Duration::from_secs(2); // Shorter gaps are taken for clock jitter
+
+/// Parses durations such as `90`, `30s`, `10m` or `1h`. Bare numbers are seconds.
+pub fn parse_duration(spec: &str) -> Option<Duration> {
+    let spec = spec.trim();
+    let (number, unit) = match spec.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => spec.split_at(index),
+        None => (spec, "s"),
+    };
+    let value: u64 = number.parse().ok()?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        _ => return None,
+    };
+    Some(Duration::from_secs(value.checked_mul(multiplier)?))
+}
+
+/// Returns how long it is until the next occurrence of a local `HH:MM` wall-clock time.
+pub fn until_local_time(spec: &str) -> Option<Duration> {
+    let (hours, minutes) = spec.trim().split_once(':')?;
+    let hours: u64 = hours.parse().ok()?;
+    let minutes: u64 = minutes.parse().ok()?;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+
+    let target = hours * 60 * 60 + minutes * 60;
+    let now = local_seconds_since_midnight();
+    let wait = if target > now {
+        target - now
+    } else {
+        target + SECS_PER_DAY - now
+    };
+    Some(Duration::from_secs(wait))
+}
+
+fn local_seconds_since_midnight() -> u64 {
+    let tm = local_now();
+    (tm.tm_hour * 60 * 60 + tm.tm_min * 60 + tm.tm_sec) as u64
+}
+
+/// Returns the current broken-down local time.
+pub fn local_now() -> libc::tm {
+    local_after(Duration::ZERO)
+}
+
+/// Returns the broken-down local time `delay` from now.
+fn local_after(delay: Duration) -> libc::tm {
+    // SAFETY: `time` accepts a null pointer.
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    local_at(now + delay.as_secs() as libc::time_t)
+}
+
+/// Returns the broken-down local time at `at` seconds since the epoch.
+fn local_at(at: libc::time_t) -> libc::tm {
+    // SAFETY: `localtime_r` only writes to `tm`.
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&at, &mut tm);
+        tm
+    }
+}
+
+/// Formats the current local date as `2026-10-14`.
+pub fn local_date() -> String {
+    format_date(&local_now())
+}
+
+/// Formats yesterday's local date as `2026-10-13`.
+pub fn local_yesterday() -> String {
+    day_before(local_now())
+}
+
+/// The local date of the calendar day before `tm`. Days are stepped through the calendar
+/// rather than 24 hours back, since a day with a DST change is an hour shorter or longer.
+fn day_before(mut tm: libc::tm) -> String {
+    tm.tm_mday -= 1;
+    tm.tm_isdst = -1;
+    // SAFETY: `mktime` normalizes the fields of `tm` in place, e.g. day 0 to the month before.
+    unsafe {
+        libc::mktime(&mut tm);
+    }
+    format_date(&tm)
+}
+
+fn format_date(tm: &libc::tm) -> String {
+    format!(
+        "{:04}-{:02}-{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday
+    )
+}
+
+/// Formats the current local time of day as `09:05`.
+pub fn local_time() -> String {
+    local_time_after(Duration::ZERO)
+}
+
+/// Formats the local time of day `delay` from now, e.g. `09:30` for an end time.
+pub fn local_time_after(delay: Duration) -> String {
+    let tm = local_after(delay);
+    format!("{:02}:{:02}", tm.tm_hour, tm.tm_min)
+}
+
+/// Formats the current local time as `2026-10-14 09:05:00 +0200`.
+pub fn local_timestamp() -> String {
+    let tm = local_now();
+    let offset_minutes = tm.tm_gmtoff / 60;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} {}{:02}{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        if offset_minutes < 0 { '-' } else { '+' },
+        offset_minutes.abs() / 60,
+        offset_minutes.abs() % 60
+    )
+}
+
+/// Formats the current local time as `2026-10-14T09:05:00+02:00`, for records that other
+/// programs read.
+pub fn local_rfc3339() -> String {
+    let tm = local_now();
+    let offset_minutes = tm.tm_gmtoff / 60;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        if offset_minutes < 0 { '-' } else { '+' },
+        offset_minutes.abs() / 60,
+        offset_minutes.abs() % 60
+    )
+}
+
+/// What the timer does about time the machine spent suspended.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OnSuspend {
+    #[default]
+    Continue, // Count the time asleep, as a wall clock would
+    Pause, // Pause a running phase where it was when the machine went to sleep
+}
+
+impl FromStr for OnSuspend {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "continue" => Ok(OnSuspend::Continue),
+            "pause" => Ok(OnSuspend::Pause),
+            _ => Err(format!("Unknown suspend policy {}", name)),
+        }
+    }
+}
+
+/// Notices suspends by comparing `Instant`, which stands still while the machine sleeps, with
+/// `CLOCK_BOOTTIME`, which does not.
+pub struct SuspendWatch {
+    instant: Instant,
+    boottime: Duration,
+}
+
+impl SuspendWatch {
+    pub fn new() -> Self {
+        Self {
+            instant: Instant::now(),
+            boottime: boottime(),
+        }
+    }
+
+    /// How long the machine was asleep since the last call, if it was.
+    pub fn slept(&mut self) -> Option<Duration> {
+        let (instant, boottime) = (Instant::now(), boottime());
+        let awake = instant.duration_since(self.instant);
+        let slept = boottime.saturating_sub(self.boottime).saturating_sub(awake);
+        self.instant = instant;
+        self.boottime = boottime;
+        Some(slept).filter(|slept| *slept >= MIN_SUSPEND)
+    }
+}
+
+fn boottime() -> Duration {
+    // SAFETY: `clock_gettime` only writes to `now`.
+    let now = unsafe {
+        let mut now: libc::timespec = std::mem::zeroed();
+        libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut now);
+        now
+    };
+    Duration::new(now.tv_sec as u64, now.tv_nsec as u32)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::{
+        ffi::OsString,
+        sync::{Mutex, MutexGuard, PoisonError},
+    };
+
+    extern "C" {
+        fn tzset();
+    }
+
+    /// Held by every test that reads the local time, so none of them runs while
+    /// [`local_days_follow_the_calendar_across_midnight_and_dst`] has the time zone changed.
+    pub fn lock_local_time() -> MutexGuard<'static, ()> {
+        static LOCAL_TIME: Mutex<()> = Mutex::new(());
+        LOCAL_TIME.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Puts `TZ` back as it was when dropped, even when the test fails.
+    struct RestoreTimeZone(Option<OsString>);
+
+    impl Drop for RestoreTimeZone {
+        fn drop(&mut self) {
+            match &self.0 {
+                Some(tz) => std::env::set_var("TZ", tz),
+                None => std::env::remove_var("TZ"),
+            }
+            // SAFETY: `tzset` only rereads `TZ`.
+            unsafe { tzset() };
+        }
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration(" 10m "), Some(Duration::from_secs(600)));
+        assert_eq!(parse_duration("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("soon"), None);
+        assert_eq!(parse_duration("5d"), None);
+    }
+
+    #[test]
+    fn durations_too_long_to_count_in_seconds_are_rejected() {
+        let max = u64::MAX.to_string();
+        assert_eq!(parse_duration(&max), Some(Duration::from_secs(u64::MAX)));
+        assert_eq!(parse_duration(&format!("{}m", max)), None);
+        assert_eq!(parse_duration(&format!("{}h", u64::MAX / 60)), None);
+        assert_eq!(parse_duration("99999999999999999999s"), None);
+    }
+
+    /// Runs in Central European time, with its DST rules spelled out so the test needs no
+    /// tzdata. The time zone is process-wide state, so the test holds [`lock_local_time`] while
+    /// it is changed and puts it back afterwards.
+    #[test]
+    fn local_days_follow_the_calendar_across_midnight_and_dst() {
+        let _local_time = lock_local_time();
+        let _restore = RestoreTimeZone(std::env::var_os("TZ"));
+        std::env::set_var("TZ", "CET-1CEST,M3.5.0,M10.5.0/3");
+        // SAFETY: `tzset` only rereads `TZ`.
+        unsafe { tzset() };
+        let date = |at| format_date(&local_at(at));
+        let yesterday = |at| day_before(local_at(at));
+
+        // 2026-03-28 23:30 UTC is already the 29th locally.
+        assert_eq!(date(1_774_740_600), "2026-03-29");
+        // 00:30 on the day after DST starts; 24 hours back is still two days ago.
+        assert_eq!(date(1_774_823_400), "2026-03-30");
+        assert_eq!(yesterday(1_774_823_400), "2026-03-29");
+        // 23:30 on the 25-hour day DST ends; 24 hours back is still the same day.
+        assert_eq!(date(1_792_967_400), "2026-10-25");
+        assert_eq!(yesterday(1_792_967_400), "2026-10-24");
+        // 00:30 on New Year's Day, locally.
+        assert_eq!(date(1_798_759_800), "2027-01-01");
+        assert_eq!(yesterday(1_798_759_800), "2026-12-31");
+    }
+}
@@ -0,0 +1,245 @@
+use std::{fs, io, path::PathBuf};
+
+/// A value from the config file.
+#[derive(Clone, Debug)]
+pub enum ConfigValue {
+    Bool(bool),
+    Scalar(String), // A string or a number, as written
+    List(Vec<String>),
+}
+
+/// `$XDG_CONFIG_HOME/waybar-pomodoro/config.toml`.
+fn config_path() -> Option<PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join("waybar-pomodoro")
+            .join("config.toml"),
+    )
+}
+
+/// Reads the settings from the config file, which may be missing.
+pub fn load() -> Result<Vec<(String, ConfigValue)>, String> {
+    let Some(path) = config_path() else {
+        return Ok(Vec::new());
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => parse(&text).map_err(|err| format!("{}: {}", path.display(), err)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(format!("{}: {}", path.display(), err)),
+    }
+}
+
+/// Parses the flat subset of TOML the settings need: `key = value` lines with strings,
+/// numbers, booleans and single-line arrays, plus comments.
+pub fn parse(text: &str) -> Result<Vec<(String, ConfigValue)>, String> {
+    let mut settings = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let at = |err: &str| format!("line {}: {}", index + 1, err);
+        if line.starts_with('[') {
+            return Err(at("tables are not supported, settings go at the top level"));
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| at("expected key = value"))?;
+        let key = key.trim().trim_matches('"').replace('_', "-");
+        let value = value.trim();
+        let value = if let Some(items) = value.strip_prefix('[') {
+            let items = items
+                .strip_suffix(']')
+                .ok_or_else(|| at("arrays must close on the same line"))?;
+            let items = split_items(items)
+                .into_iter()
+                .map(|item| scalar(item.trim()).ok_or_else(|| at("invalid array item")))
+                .collect::<Result<_, _>>()?;
+            ConfigValue::List(items)
+        } else if value == "true" || value == "false" {
+            ConfigValue::Bool(value == "true")
+        } else {
+            ConfigValue::Scalar(scalar(value).ok_or_else(|| at("invalid value"))?)
+        };
+        settings.push((key, value));
+    }
+    Ok(settings)
+}
+
+/// The characters of a line that are not inside a string, with their byte offsets.
+fn outside_strings(line: &str) -> Vec<(usize, char)> {
+    let mut outside = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match quote {
+            Some('"') if c == '\\' && !escaped => {
+                escaped = true;
+                continue;
+            }
+            Some(open) if c == open && !escaped => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None => outside.push((index, c)),
+        }
+        escaped = false;
+    }
+    outside
+}
+
+/// Drops a `#` comment that is not inside a string.
+fn strip_comment(line: &str) -> &str {
+    match outside_strings(line).into_iter().find(|(_, c)| *c == '#') {
+        Some((index, _)) => &line[..index],
+        None => line,
+    }
+}
+
+/// Splits array items on the commas between them.
+fn split_items(items: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (index, _) in outside_strings(items)
+        .into_iter()
+        .filter(|(_, c)| *c == ',')
+    {
+        parts.push(&items[start..index]);
+        start = index + 1;
+    }
+    parts.push(&items[start..]);
+    parts.retain(|part| !part.trim().is_empty());
+    parts
+}
+
+/// A basic or literal string, or a bare number.
+fn scalar(value: &str) -> Option<String> {
+    if let Some(literal) = value.strip_prefix('\'') {
+        let literal = literal.strip_suffix('\'')?;
+        return (!literal.contains('\'')).then(|| literal.to_string());
+    }
+    let Some(basic) = value.strip_prefix('"') else {
+        let is_number = !value.is_empty()
+            && value
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | '_'));
+        return is_number.then(|| value.replace('_', ""));
+    };
+    let basic = basic.strip_suffix('"')?;
+    let mut unescaped = String::new();
+    let mut chars = basic.chars();
+    while let Some(c) = chars.next() {
+        if c == '"' {
+            return None; // The string ended early, e.g. two items missing a comma
+        }
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => unescaped.push('\n'),
+            't' => unescaped.push('\t'),
+            c @ ('"' | '\\') => unescaped.push(c),
+            _ => return None,
+        }
+    }
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn only(text: &str) -> ConfigValue {
+        let mut settings = parse(text).unwrap();
+        assert_eq!(settings.len(), 1, "{:?}", settings);
+        settings.remove(0).1
+    }
+
+    fn scalar_of(text: &str) -> String {
+        match only(text) {
+            ConfigValue::Scalar(value) => value,
+            other => panic!("{:?} is not a scalar", other),
+        }
+    }
+
+    fn list_of(text: &str) -> Vec<String> {
+        match only(text) {
+            ConfigValue::List(items) => items,
+            other => panic!("{:?} is not a list", other),
+        }
+    }
+
+    #[test]
+    fn keys_use_dashes_and_comments_are_dropped() {
+        let settings =
+            parse("# Focus\n\nshort_break = \"10m\" # longer\nno-icons = true\n").unwrap();
+        let keys: Vec<_> = settings.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["short-break", "no-icons"]);
+        assert!(matches!(settings[1].1, ConfigValue::Bool(true)));
+    }
+
+    #[test]
+    fn a_hash_inside_a_string_is_not_a_comment() {
+        assert_eq!(scalar_of("color = \"#ff6347\" # tomato"), "#ff6347");
+        assert_eq!(scalar_of("color = '#ff6347'"), "#ff6347");
+        assert_eq!(
+            list_of("distraction = [\"#general\", 'C#'] # chat"),
+            ["#general", "C#"]
+        );
+    }
+
+    #[test]
+    fn escaped_quotes_stay_inside_the_string() {
+        assert_eq!(
+            scalar_of(r#"format = "say \"hi\" # now""#),
+            "say \"hi\" # now"
+        );
+        assert_eq!(scalar_of(r#"format = "a\\" # b"#), "a\\");
+        assert_eq!(scalar_of(r#"format = "tab\there\n""#), "tab\there\n");
+        assert!(parse(r#"format = "\q""#).is_err());
+    }
+
+    #[test]
+    fn numbers_are_kept_as_written_without_underscores() {
+        assert_eq!(scalar_of("daily-goal = 8"), "8");
+        assert_eq!(scalar_of("rate-limit = 1_000"), "1000");
+        assert!(parse("work = 25 minutes").is_err());
+    }
+
+    #[test]
+    fn arrays_allow_trailing_commas_and_quotes_in_items() {
+        assert_eq!(
+            list_of("distraction = [\"firefox\", \"YouTube\",]"),
+            ["firefox", "YouTube"]
+        );
+        assert_eq!(list_of("distraction = []"), Vec::<String>::new());
+        assert_eq!(
+            list_of(r#"distraction = ["a, b", 'say "hi"', "\"quoted\"", 7]"#),
+            ["a, b", "say \"hi\"", "\"quoted\"", "7"]
+        );
+        assert!(parse("distraction = [\"firefox\",").is_err());
+        assert!(parse("distraction = [\"firefox\" \"YouTube\"]").is_err());
+    }
+
+    #[test]
+    fn tables_are_rejected() {
+        let err = parse("work = \"25m\"\n[colors]\nwork = \"red\"").unwrap_err();
+        assert_eq!(
+            err,
+            "line 2: tables are not supported, settings go at the top level"
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert_eq!(
+            parse("# Focus\nwork = \"25m\"\n\nshort-break\n").unwrap_err(),
+            "line 4: expected key = value"
+        );
+        assert_eq!(parse("work = \"25m").unwrap_err(), "line 1: invalid value");
+        assert_eq!(
+            parse("\n\ndistraction = [\"a\"").unwrap_err(),
+            "line 3: arrays must close on the same line"
+        );
+    }
+}
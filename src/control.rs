@@ -0,0 +1,195 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use serde_json::json;
+
+use crate::status::Status;
+
+/// How a command is used, which decides what `bindings` offers it for.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Usage {
+    Action, // Changes the timer and takes no argument, e.g. `toggle`
+    Adjust, // Takes a duration, e.g. `add 1m`
+    Text,   // Takes text typed by the user, e.g. `note`
+    Query,  // Prints something, e.g. `stats`
+    Report, // Tells the timer about the session, e.g. `idle` from an idle daemon
+    Daemon, // Controls the daemon itself
+}
+
+/// Commands accepted on the socket, by their first word.
+pub const COMMANDS: &[(&str, Usage)] = &[
+    ("start", Usage::Action),
+    ("pause", Usage::Action),
+    ("toggle", Usage::Action),
+    ("skip", Usage::Action),
+    ("reset", Usage::Action),
+    ("add", Usage::Adjust),
+    ("sub", Usage::Adjust),
+    ("errors", Usage::Query),
+    ("stats", Usage::Query),
+    ("report", Usage::Query),
+    ("audit", Usage::Query),
+    ("dump", Usage::Query),
+    ("note", Usage::Text),
+    ("capture", Usage::Text),
+    ("queue", Usage::Text),
+    ("task", Usage::Text),
+    ("done", Usage::Action),
+    ("status", Usage::Query),
+    ("idle", Usage::Report),
+    ("active", Usage::Report),
+    ("upgrade", Usage::Daemon),
+    ("stop", Usage::Daemon),
+];
+
+/// What wakes the timer thread before its next tick.
+pub enum Event {
+    Command(Request),
+    Wake, // Something outside the thread changed the timer, e.g. a notification action
+}
+
+/// A command waiting for the timer thread, with the connection to answer on.
+pub struct Request {
+    pub command: String,
+    reply_to: mpsc::Sender<String>,
+}
+
+impl Request {
+    /// A request for `command`, with where its response will arrive.
+    pub fn new(command: String) -> (Self, mpsc::Receiver<String>) {
+        let (reply_to, response) = mpsc::channel();
+        (Self { command, reply_to }, response)
+    }
+
+    /// Answers the client with one JSON line: whether the command was accepted, its reply and
+    /// the timer's state afterwards.
+    pub fn respond(&self, ok: bool, reply: &str, status: &Status) {
+        let response = json!({ "ok": ok, "reply": reply, "status": status });
+        // The client may have given up waiting, which is no reason to stop the timer.
+        let _ = self.reply_to.send(response.to_string());
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/waybar-pomodoro/socket`, or `socket.<namespace>`, falling back to
+/// `pomodoro_socket` in the working directory when there is no runtime directory.
+pub fn socket_path(namespace: Option<&str>) -> PathBuf {
+    let name = match namespace {
+        Some(namespace) => format!("socket.{}", namespace),
+        None => "socket".to_string(),
+    };
+    match dirs::runtime_dir() {
+        Some(dir) => dir.join("waybar-pomodoro").join(name),
+        None => PathBuf::from(format!("pomodoro_{}", name)),
+    }
+}
+
+/// The command line that reaches the timer of `namespace`, for bars and key bindings that run
+/// commands themselves.
+pub fn ctl_command(namespace: Option<&str>) -> String {
+    match namespace {
+        Some(namespace) => format!("waybar-pomodoro --namespace {} ctl", namespace),
+        None => "waybar-pomodoro ctl".to_string(),
+    }
+}
+
+/// Listens on the socket, replacing one left behind by a timer that is gone.
+pub fn bind(path: &Path) -> Result<UnixListener, String> {
+    if UnixStream::connect(path).is_ok() {
+        return Err(format!("A timer is already running on {}", path.display()));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+    }
+    fs::remove_file(path).ok();
+    UnixListener::bind(path).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// Accepts clients in the background, each on its own thread so a slow one cannot hold up the
+/// others.
+pub fn serve(listener: UnixListener, events: mpsc::Sender<Event>) {
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let events = events.clone();
+            thread::spawn(move || serve_client(stream, &events));
+        }
+    });
+}
+
+/// Passes each line the client sends to the timer thread as a command and writes back the
+/// response to it.
+fn serve_client(stream: UnixStream, events: &mpsc::Sender<Event>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let response = match normalize(&line) {
+            Ok(command) => {
+                let (request, response) = Request::new(command);
+                if events.send(Event::Command(request)).is_err() {
+                    break;
+                }
+                match response.recv() {
+                    Ok(response) => response,
+                    Err(_) => break,
+                }
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                json!({ "ok": false, "reply": err }).to_string()
+            }
+        };
+        if writeln!(writer, "{}", response).is_err() {
+            break;
+        }
+    }
+}
+
+/// How the command is used, by its first word.
+pub fn usage(command: &str) -> Option<Usage> {
+    let name = command.split(' ').next().unwrap_or_default();
+    COMMANDS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, usage)| *usage)
+}
+
+/// Checks the command name and lower-cases it. The text of a note, a capture or a task keeps its
+/// case.
+fn normalize(line: &str) -> Result<String, String> {
+    let line = line.trim();
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let name = name.to_lowercase();
+    match COMMANDS.iter().find(|(command, _)| *command == name) {
+        Some((_, Usage::Text)) => Ok(format!("{} {}", name, rest.trim())),
+        Some(_) => Ok(line.to_lowercase()),
+        None => Err(format!("Invalid command {}", line)),
+    }
+}
+
+/// Sends a command to the running timer and waits for its reply. Returns the reply and whether
+/// the command was accepted.
+pub fn send(path: &Path, command: &str) -> Result<(bool, String), String> {
+    let mut stream = UnixStream::connect(path)
+        .map_err(|_| format!("No timer is running on {}", path.display()))?;
+    writeln!(stream, "{}", command).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let mut line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut line)
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    if line.is_empty() {
+        return Err("The timer closed the connection without replying".to_string());
+    }
+    let response: serde_json::Value =
+        serde_json::from_str(&line).map_err(|err| format!("Invalid response: {}", err))?;
+    let reply = response["reply"].as_str().unwrap_or_default().to_string();
+    Ok((response["ok"] == true, reply))
+}
@@ -0,0 +1,582 @@
+use std::{
+    collections::VecDeque,
+    env,
+    io::{BufRead, BufReader, Read, Write},
+    os::{linux::net::SocketAddrExt, unix::net::SocketAddr, unix::net::UnixStream},
+    time::{Duration, Instant},
+};
+
+// How long a call may wait for the bus, so a daemon that hangs fails the call instead.
+const CALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+const SIGNAL: u8 = 4;
+
+// Header fields, by their codes in the wire format.
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SIGNATURE: u8 = 8;
+
+/// A message received from the bus. Only the parts the notification calls need are kept.
+#[derive(Debug)]
+pub struct Message {
+    kind: u8,
+    reply_serial: Option<u32>,
+    pub member: Option<String>,
+    error_name: Option<String>,
+    big_endian: bool,
+    body: Vec<u8>,
+}
+
+impl Message {
+    /// Reads the body, which starts 8-byte aligned like the reader's offsets.
+    pub fn body(&self) -> Reader<'_> {
+        Reader {
+            data: &self.body,
+            pos: 0,
+            big_endian: self.big_endian,
+        }
+    }
+
+    pub fn is_signal(&self) -> bool {
+        self.kind == SIGNAL
+    }
+}
+
+/// A connection to the session bus, speaking just enough of the D-Bus wire protocol for
+/// method calls and the signals they trigger.
+pub struct Connection {
+    stream: BufReader<UnixStream>,
+    serial: u32,
+    pending: VecDeque<Message>, // Signals that arrived while waiting for a reply
+}
+
+impl Connection {
+    /// Connects to `$DBUS_SESSION_BUS_ADDRESS`, or `$XDG_RUNTIME_DIR/bus` without it.
+    pub fn session() -> Result<Self, String> {
+        let stream = connect()?;
+        let mut connection = Self {
+            stream: BufReader::new(stream),
+            serial: 0,
+            pending: VecDeque::new(),
+        };
+        connection.authenticate()?;
+        connection.call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "Hello",
+            "",
+            Vec::new(),
+        )?;
+        Ok(connection)
+    }
+
+    /// Authenticates as the user running the process.
+    fn authenticate(&mut self) -> Result<(), String> {
+        let uid = nix::unistd::getuid().to_string();
+        let hex: String = uid.bytes().map(|byte| format!("{:02x}", byte)).collect();
+        let stream = self.stream.get_mut();
+        stream
+            .write_all(format!("\0AUTH EXTERNAL {}\r\n", hex).as_bytes())
+            .map_err(|err| err.to_string())?;
+        let mut line = String::new();
+        self.stream
+            .read_line(&mut line)
+            .map_err(|err| err.to_string())?;
+        if !line.starts_with("OK ") {
+            return Err(format!("The bus refused to authenticate: {}", line.trim()));
+        }
+        self.stream
+            .get_mut()
+            .write_all(b"BEGIN\r\n")
+            .map_err(|err| err.to_string())
+    }
+
+    /// Calls a method and waits for its reply, keeping any signals for [`Self::next_signal`].
+    pub fn call(
+        &mut self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        signature: &str,
+        body: Vec<u8>,
+    ) -> Result<Message, String> {
+        self.serial += 1;
+        let serial = self.serial;
+        let message = method_call(
+            serial,
+            destination,
+            path,
+            interface,
+            member,
+            signature,
+            &body,
+        );
+        self.stream
+            .get_ref()
+            .set_read_timeout(Some(CALL_TIMEOUT))
+            .map_err(|err| err.to_string())?;
+        self.stream
+            .get_mut()
+            .write_all(&message)
+            .map_err(|err| err.to_string())?;
+        loop {
+            let message = self.read_message()?;
+            match message.kind {
+                METHOD_RETURN if message.reply_serial == Some(serial) => return Ok(message),
+                ERROR if message.reply_serial == Some(serial) => {
+                    let detail = message.body().string().unwrap_or_default();
+                    let name = message.error_name.unwrap_or_default();
+                    return Err(format!("{}: {}", name, detail));
+                }
+                SIGNAL => self.pending.push_back(message),
+                _ => {}
+            }
+        }
+    }
+
+    /// Waits until `deadline` for the next signal matched by an earlier `AddMatch`. Returns
+    /// `None` when none came in time; the connection should not be used after that, as it may
+    /// have stopped halfway through a message.
+    pub fn next_signal(&mut self, deadline: Instant) -> Result<Option<Message>, String> {
+        if let Some(message) = self.pending.pop_front() {
+            return Ok(Some(message));
+        }
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(None);
+            }
+            self.stream
+                .get_ref()
+                .set_read_timeout(Some(left))
+                .map_err(|err| err.to_string())?;
+            match self.read_message() {
+                Ok(message) if message.is_signal() => return Ok(Some(message)),
+                Ok(_) => {}
+                Err(_) if Instant::now() >= deadline => return Ok(None),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn read_message(&mut self) -> Result<Message, String> {
+        let mut fixed = [0u8; 16];
+        self.stream
+            .read_exact(&mut fixed)
+            .map_err(|err| err.to_string())?;
+        let big_endian = match fixed[0] {
+            b'l' => false,
+            b'B' => true,
+            other => return Err(format!("Unknown byte order {:?}", other as char)),
+        };
+        let number = |bytes: &[u8]| {
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            if big_endian {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            }
+        };
+        let body_length = number(&fixed[4..8]) as usize;
+        let fields_length = number(&fixed[12..16]) as usize;
+
+        // The fields array is padded so the body starts on an 8-byte boundary.
+        let mut rest = vec![0u8; align(16 + fields_length, 8) - 16 + body_length];
+        self.stream
+            .read_exact(&mut rest)
+            .map_err(|err| err.to_string())?;
+        let mut header = fixed.to_vec();
+        header.extend_from_slice(&rest[..fields_length]);
+        let body = rest[rest.len() - body_length..].to_vec();
+
+        let mut message = Message {
+            kind: fixed[1],
+            reply_serial: None,
+            member: None,
+            error_name: None,
+            big_endian,
+            body,
+        };
+        let mut fields = Reader {
+            data: &header,
+            pos: 16,
+            big_endian,
+        };
+        while fields.pos < header.len() {
+            fields.align(8);
+            let code = fields.byte()?;
+            let signature = fields.signature()?;
+            match (code, signature.as_str()) {
+                (FIELD_REPLY_SERIAL, "u") => message.reply_serial = Some(fields.u32()?),
+                (FIELD_MEMBER, "s") => message.member = Some(fields.string()?),
+                (FIELD_ERROR_NAME, "s") => message.error_name = Some(fields.string()?),
+                (_, "s" | "o") => {
+                    fields.string()?;
+                }
+                (_, "g") => {
+                    fields.signature()?;
+                }
+                (_, "u") => {
+                    fields.u32()?;
+                }
+                (_, other) => return Err(format!("Unexpected header field type {}", other)),
+            }
+        }
+        Ok(message)
+    }
+}
+
+fn connect() -> Result<UnixStream, String> {
+    let address = env::var("DBUS_SESSION_BUS_ADDRESS").ok().or_else(|| {
+        let runtime = env::var("XDG_RUNTIME_DIR").ok()?;
+        Some(format!("unix:path={}/bus", runtime))
+    });
+    let Some(address) = address else {
+        return Err("No session bus: DBUS_SESSION_BUS_ADDRESS is not set".to_string());
+    };
+    // Several addresses may be listed, to be tried in order.
+    for candidate in address.split(';') {
+        let Some(options) = candidate.strip_prefix("unix:") else {
+            continue;
+        };
+        for option in options.split(',') {
+            let stream = if let Some(path) = option.strip_prefix("path=") {
+                UnixStream::connect(path)
+            } else if let Some(name) = option.strip_prefix("abstract=") {
+                SocketAddr::from_abstract_name(name)
+                    .and_then(|addr| UnixStream::connect_addr(&addr))
+            } else {
+                continue;
+            };
+            let Ok(stream) = stream else {
+                continue;
+            };
+            stream
+                .set_read_timeout(Some(CALL_TIMEOUT))
+                .and_then(|_| stream.set_write_timeout(Some(CALL_TIMEOUT)))
+                .map_err(|err| err.to_string())?;
+            return Ok(stream);
+        }
+    }
+    Err(format!(
+        "Could not connect to the session bus at {}",
+        address
+    ))
+}
+
+fn align(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+fn method_call(
+    serial: u32,
+    destination: &str,
+    path: &str,
+    interface: &str,
+    member: &str,
+    signature: &str,
+    body: &[u8],
+) -> Vec<u8> {
+    let mut header = Writer::default();
+    header.byte(b'l');
+    header.byte(METHOD_CALL);
+    header.byte(0);
+    header.byte(1);
+    header.u32(body.len() as u32);
+    header.u32(serial);
+    let mut fields = vec![
+        (FIELD_PATH, "o", path),
+        (FIELD_INTERFACE, "s", interface),
+        (FIELD_MEMBER, "s", member),
+        (FIELD_DESTINATION, "s", destination),
+    ];
+    if !signature.is_empty() {
+        fields.push((FIELD_SIGNATURE, "g", signature));
+    }
+    header.array(8, |header| {
+        for (code, kind, value) in &fields {
+            header.align(8);
+            header.byte(*code);
+            header.signature(kind);
+            match *kind {
+                "g" => header.signature(value),
+                _ => header.string(value),
+            }
+        }
+    });
+    header.align(8);
+    let mut message = header.data;
+    message.extend_from_slice(body);
+    message
+}
+
+/// Marshals values in little-endian order, aligned from the start of the buffer.
+#[derive(Default)]
+pub struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    fn align(&mut self, alignment: usize) {
+        self.data.resize(align(self.data.len(), alignment), 0);
+    }
+
+    pub fn byte(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn i32(&mut self, value: i32) {
+        self.align(4);
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.data.extend_from_slice(value.as_bytes());
+        self.data.push(0);
+    }
+
+    pub fn signature(&mut self, value: &str) {
+        self.data.push(value.len() as u8);
+        self.data.extend_from_slice(value.as_bytes());
+        self.data.push(0);
+    }
+
+    /// Writes an array whose elements are aligned to `alignment`, e.g. 8 for dict entries.
+    pub fn array(&mut self, alignment: usize, items: impl FnOnce(&mut Self)) {
+        self.u32(0);
+        let length_at = self.data.len() - 4;
+        self.align(alignment);
+        let start = self.data.len();
+        items(self);
+        let length = (self.data.len() - start) as u32;
+        self.data[length_at..length_at + 4].copy_from_slice(&length.to_le_bytes());
+    }
+}
+
+/// Unmarshals values from a message.
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn align(&mut self, alignment: usize) {
+        self.pos = align(self.pos, alignment);
+    }
+
+    fn take(&mut self, count: usize) -> Result<&[u8], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + count)
+            .ok_or("Truncated message")?;
+        self.pos += count;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        self.align(4);
+        let big_endian = self.big_endian;
+        let bytes = self.take(4)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Ok(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    pub fn string(&mut self) -> Result<String, String> {
+        let length = self.u32()? as usize;
+        let text = String::from_utf8_lossy(self.take(length)?).into_owned();
+        self.take(1)?;
+        Ok(text)
+    }
+
+    fn signature(&mut self) -> Result<String, String> {
+        let length = self.byte()? as usize;
+        let text = String::from_utf8_lossy(self.take(length)?).into_owned();
+        self.take(1)?;
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Captured from dbus-daemon with `dbus-monitor --pcap`: the reply to a `Notify` call with
+    // reply serial 2, the bus refusing a call to a name nobody owns, and a notification daemon
+    // sending `ActionInvoked(7, "skip")`.
+    const REPLY: &[u8] = include_bytes!("../tests/fixtures/dbus-method-return.bin");
+    const ERROR_REPLY: &[u8] = include_bytes!("../tests/fixtures/dbus-error.bin");
+    const ACTION_INVOKED: &[u8] = include_bytes!("../tests/fixtures/dbus-signal.bin");
+
+    /// A connection whose bus sends `messages` and nothing else.
+    fn connection(messages: &[&[u8]]) -> (Connection, UnixStream) {
+        let (ours, mut bus) = UnixStream::pair().unwrap();
+        for message in messages {
+            bus.write_all(message).unwrap();
+        }
+        let connection = Connection {
+            stream: BufReader::new(ours),
+            serial: 1, // The next call is the one the captured replies answer
+            pending: VecDeque::new(),
+        };
+        (connection, bus)
+    }
+
+    fn array_end(reader: &mut Reader, alignment: usize) -> usize {
+        let length = reader.u32().unwrap() as usize;
+        reader.align(alignment);
+        reader.pos + length
+    }
+
+    #[test]
+    fn values_read_back_as_written_at_every_offset() {
+        for offset in 0..8 {
+            let mut writer = Writer::default();
+            (0..offset).for_each(|_| writer.byte(0xff));
+            writer.u32(0xdead_beef);
+            writer.string("Take a short break.");
+            writer.array(4, |writer| {
+                writer.string("skip");
+                writer.string("Skip");
+            });
+            writer.array(8, |_| {});
+            writer.byte(FIELD_MEMBER);
+            writer.signature("s");
+            writer.string("Notify");
+            writer.byte(FIELD_REPLY_SERIAL);
+            writer.signature("u");
+            writer.u32(42);
+            let data = writer.into_bytes();
+
+            let mut reader = Reader {
+                data: &data,
+                pos: offset,
+                big_endian: false,
+            };
+            assert_eq!(reader.u32(), Ok(0xdead_beef), "offset {}", offset);
+            assert_eq!(reader.string().unwrap(), "Take a short break.");
+            let end = array_end(&mut reader, 4);
+            let mut actions = Vec::new();
+            while reader.pos < end {
+                actions.push(reader.string().unwrap());
+            }
+            assert_eq!(actions, ["skip", "Skip"], "offset {}", offset);
+            let end = array_end(&mut reader, 8);
+            assert_eq!(reader.pos, end, "offset {}", offset);
+            // Variants, as the header fields carry them.
+            assert_eq!(reader.byte(), Ok(FIELD_MEMBER));
+            assert_eq!(reader.signature().unwrap(), "s");
+            assert_eq!(reader.string().unwrap(), "Notify");
+            assert_eq!(reader.byte(), Ok(FIELD_REPLY_SERIAL));
+            assert_eq!(reader.signature().unwrap(), "u");
+            assert_eq!(reader.u32(), Ok(42), "offset {}", offset);
+            assert_eq!(reader.pos, data.len());
+        }
+    }
+
+    #[test]
+    fn truncated_values_are_an_error() {
+        let mut writer = Writer::default();
+        writer.string("Take a short break.");
+        let data = writer.into_bytes();
+        let mut reader = Reader {
+            data: &data[..10],
+            pos: 0,
+            big_endian: false,
+        };
+        assert!(reader.string().is_err());
+    }
+
+    #[test]
+    fn parses_a_method_return() {
+        let (mut connection, _bus) = connection(&[REPLY]);
+        let message = connection.read_message().unwrap();
+        assert_eq!(message.kind, METHOD_RETURN);
+        assert_eq!(message.reply_serial, Some(2));
+        assert_eq!(message.body().u32(), Ok(23));
+    }
+
+    #[test]
+    fn parses_an_error() {
+        let (mut connection, _bus) = connection(&[ERROR_REPLY]);
+        let message = connection.read_message().unwrap();
+        assert_eq!(message.kind, ERROR);
+        assert_eq!(message.reply_serial, Some(2));
+        assert_eq!(
+            message.error_name.as_deref(),
+            Some("org.freedesktop.DBus.Error.ServiceUnknown")
+        );
+        assert!(message
+            .body()
+            .string()
+            .unwrap()
+            .starts_with("The name org.example.Gone"));
+    }
+
+    #[test]
+    fn parses_a_signal() {
+        let (mut connection, _bus) = connection(&[ACTION_INVOKED]);
+        let message = connection.read_message().unwrap();
+        assert!(message.is_signal());
+        assert_eq!(message.member.as_deref(), Some("ActionInvoked"));
+        let mut body = message.body();
+        assert_eq!(body.u32(), Ok(7));
+        assert_eq!(body.string().unwrap(), "skip");
+    }
+
+    #[test]
+    fn signals_before_the_reply_wait_for_next_signal() {
+        let (mut connection, _bus) = connection(&[ACTION_INVOKED, REPLY]);
+        let reply = connection
+            .call("org.example", "/", "org.example", "Ping", "", Vec::new())
+            .unwrap();
+        assert_eq!(reply.body().u32(), Ok(23));
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let signal = connection.next_signal(deadline).unwrap().unwrap();
+        assert_eq!(signal.member.as_deref(), Some("ActionInvoked"));
+    }
+
+    #[test]
+    fn an_error_reply_fails_the_call() {
+        let (mut connection, _bus) = connection(&[ERROR_REPLY]);
+        let err = connection
+            .call("org.example", "/", "org.example", "Ping", "", Vec::new())
+            .unwrap_err();
+        assert!(err.starts_with("org.freedesktop.DBus.Error.ServiceUnknown: "));
+    }
+
+    #[test]
+    fn next_signal_gives_up_at_the_deadline() {
+        let (mut connection, _bus) = connection(&[]);
+        let deadline = Instant::now() + Duration::from_millis(50);
+        assert!(connection.next_signal(deadline).unwrap().is_none());
+        assert!(Instant::now() >= deadline);
+    }
+}
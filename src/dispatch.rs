@@ -0,0 +1,50 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// Drops commands arriving too quickly, e.g. from a bouncing mouse button.
+pub struct RateLimiter {
+    debounce: Duration,                  // Minimum gap between two identical commands
+    rate_limit: usize,                   // Maximum number of commands per second
+    last_seen: HashMap<String, Instant>, // When each command was last accepted
+    accepted: VecDeque<Instant>,         // Accepted commands within the last second
+}
+
+impl RateLimiter {
+    pub fn new(debounce: Duration, rate_limit: usize) -> Self {
+        Self {
+            debounce,
+            rate_limit,
+            last_seen: HashMap::new(),
+            accepted: VecDeque::new(),
+        }
+    }
+
+    /// Returns whether the command should be handled, logging it when it is dropped.
+    pub fn allow(&mut self, command: &str) -> bool {
+        let now = Instant::now();
+        while self
+            .accepted
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= Duration::from_secs(1))
+        {
+            self.accepted.pop_front();
+        }
+
+        if let Some(last) = self.last_seen.get(command) {
+            if now.duration_since(*last) < self.debounce {
+                eprintln!("Dropped command {}: debounced", command);
+                return false;
+            }
+        }
+        if self.accepted.len() >= self.rate_limit {
+            eprintln!("Dropped command {}: rate limit exceeded", command);
+            return false;
+        }
+
+        self.last_seen.insert(command.to_string(), now);
+        self.accepted.push_back(now);
+        true
+    }
+}
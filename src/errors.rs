@@ -0,0 +1,110 @@
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde_json::json;
+
+const MAX_RECENT_ERRORS: usize = 20; // Number of failures kept for the `errors` command
+const ERROR_DISPLAY_SECS: u64 = 10; // How long a failure keeps the bar in the error state
+
+/// A failure reported by one of the subsystems around the timer.
+#[derive(Clone, Debug)]
+pub struct Failure {
+    pub code: Cow<'static, str>, // Short machine-readable code, e.g. `notify` or `sound`
+    pub message: String,
+    pub at: Instant,
+}
+
+impl Failure {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code: Cow::Borrowed(code),
+            message: message.into(),
+            at: Instant::now(),
+        }
+    }
+}
+
+/// Bounded log of recent failures.
+#[derive(Clone, Debug, Default)]
+pub struct ErrorLog {
+    failures: VecDeque<Failure>,
+}
+
+impl ErrorLog {
+    pub fn record(&mut self, failure: Failure) {
+        eprintln!("{}: {}", failure.code, failure.message);
+        if self.failures.len() == MAX_RECENT_ERRORS {
+            self.failures.pop_front();
+        }
+        self.failures.push_back(failure);
+    }
+
+    /// Returns the latest failure if it is recent enough to still be shown in the bar.
+    pub fn active(&self) -> Option<&Failure> {
+        self.failures
+            .back()
+            .filter(|failure| failure.at.elapsed().as_secs() < ERROR_DISPLAY_SECS)
+    }
+
+    /// The recent failures, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Failure> {
+        self.failures.iter()
+    }
+
+    /// The recent failures with how many seconds ago each happened, for the state file.
+    pub fn to_json(&self) -> serde_json::Value {
+        self.failures
+            .iter()
+            .map(|failure| {
+                json!({
+                    "code": failure.code,
+                    "message": failure.message,
+                    "secs_ago": failure.at.elapsed().as_secs(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn from_json(value: &serde_json::Value) -> Self {
+        let mut log = Self::default();
+        for failure in value.as_array().into_iter().flatten() {
+            let (Some(code), Some(message)) =
+                (failure["code"].as_str(), failure["message"].as_str())
+            else {
+                continue;
+            };
+            let ago = Duration::from_secs(failure["secs_ago"].as_u64().unwrap_or(0));
+            if log.failures.len() == MAX_RECENT_ERRORS {
+                log.failures.pop_front();
+            }
+            log.failures.push_back(Failure {
+                code: Cow::Owned(code.to_string()),
+                message: message.to_string(),
+                at: Instant::now().checked_sub(ago).unwrap_or_else(Instant::now),
+            });
+        }
+        log
+    }
+
+    /// Formats the recent failures, oldest first, one per line.
+    pub fn summary(&self) -> String {
+        if self.failures.is_empty() {
+            return "No recent errors".to_string();
+        }
+        self.failures
+            .iter()
+            .map(|failure| {
+                format!(
+                    "{}s ago [{}] {}",
+                    failure.at.elapsed().as_secs(),
+                    failure.code,
+                    failure.message
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
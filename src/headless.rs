@@ -0,0 +1,18 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once at startup by `--headless`, and read by every backend with effects outside the
+/// process, so a full run needs no desktop session.
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    HEADLESS.store(true, Ordering::Relaxed);
+}
+
+/// Logs what a backend would do when running headless, and returns true if it should not.
+pub fn skip(backend: &str, action: &str) -> bool {
+    let headless = HEADLESS.load(Ordering::Relaxed);
+    if headless {
+        eprintln!("headless {}: {}", backend, action);
+    }
+    headless
+}
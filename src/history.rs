@@ -0,0 +1,207 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    path::PathBuf,
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::json;
+
+use crate::errors::Failure;
+
+const RECENT_SESSIONS: usize = 3; // Finished work sessions shown in the tooltip
+
+/// A finished work session.
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub started: String, // Local `HH:MM` at which the session started
+    pub ended: String,   // Local `HH:MM` at which it ended
+    pub task: Option<String>,
+}
+
+impl Session {
+    /// `09:05–09:30 write report`, as shown in the tooltip, or just the times with `redact`.
+    pub fn describe(&self, redact: bool) -> String {
+        let range = format!("{}–{}", self.started, self.ended);
+        match self.task.as_ref().filter(|_| !redact) {
+            Some(task) => format!("{} {}", range, task),
+            None => range,
+        }
+    }
+}
+
+/// The latest finished work sessions of the day, oldest first.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    sessions: VecDeque<Session>,
+}
+
+impl History {
+    pub fn record(&mut self, session: Session) {
+        if self.sessions.len() == RECENT_SESSIONS {
+            self.sessions.pop_front();
+        }
+        self.sessions.push_back(session);
+    }
+
+    pub fn clear(&mut self) {
+        self.sessions.clear();
+    }
+
+    pub fn recent(&self) -> impl Iterator<Item = &Session> {
+        self.sessions.iter()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        self.sessions
+            .iter()
+            .map(|session| {
+                json!({
+                    "started": session.started,
+                    "ended": session.ended,
+                    "task": session.task,
+                })
+            })
+            .collect()
+    }
+
+    pub fn from_json(value: &serde_json::Value) -> Self {
+        let mut history = Self::default();
+        for session in value.as_array().into_iter().flatten() {
+            let (Some(started), Some(ended)) =
+                (session["started"].as_str(), session["ended"].as_str())
+            else {
+                continue;
+            };
+            history.record(Session {
+                started: started.to_string(),
+                ended: ended.to_string(),
+                task: session["task"].as_str().map(str::to_string),
+            });
+        }
+        history
+    }
+}
+
+/// A random version 4 UUID naming a phase, so that whatever picks up its record can tell a
+/// retry from a new session.
+pub fn new_session_id() -> String {
+    let mut bytes = [0u8; 16];
+    if File::open("/dev/urandom")
+        .and_then(|mut random| random.read_exact(&mut bytes))
+        .is_err()
+    {
+        // Unique enough for one machine when there is no randomness to be had.
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        bytes = (nanos ^ (u128::from(process::id()) << 96)).to_be_bytes();
+    }
+    bytes[6] = bytes[6] & 0x0f | 0x40;
+    bytes[8] = bytes[8] & 0x3f | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Append-only log of every phase that finished, one JSON object per line, at
+/// `$XDG_DATA_HOME/waybar-pomodoro/history.jsonl` or `history.<namespace>.jsonl`.
+#[derive(Clone, Debug)]
+pub struct HistoryLog {
+    path: PathBuf,
+}
+
+impl HistoryLog {
+    pub fn new(namespace: Option<&str>) -> Self {
+        let name = match namespace {
+            Some(namespace) => format!("history.{}.jsonl", namespace),
+            None => "history.jsonl".to_string(),
+        };
+        let path = match dirs::data_dir() {
+            Some(dir) => dir.join("waybar-pomodoro").join(name),
+            None => PathBuf::from(format!("pomodoro_{}", name)),
+        };
+        Self { path }
+    }
+
+    #[cfg(test)]
+    pub fn at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Appends a record such as `{"id":"3f2b…","started":"2026-10-14T09:05:00+02:00",
+    /// "ended":"2026-10-14T09:30:00+02:00","phase":"work","outcome":"completed",...}`.
+    pub fn record(&self, record: &serde_json::Value) -> Result<(), Failure> {
+        self.path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)
+            })
+            .and_then(|mut file| writeln!(file, "{}", record))
+            .map_err(|err| Failure::new("history", format!("{}: {}", self.path.display(), err)))
+    }
+
+    /// The work sessions logged on `day` (`2026-10-14`) with their notes, one per line such as
+    /// `09:05–09:30 write report (completed)` followed by `  09:12 call back Alex` for each
+    /// note. Tasks are left out with `redact`.
+    pub fn report(&self, day: &str, redact: bool) -> Result<String, Failure> {
+        let failed = |err: std::io::Error| {
+            Failure::new("history", format!("{}: {}", self.path.display(), err))
+        };
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(String::new()),
+            Err(err) => return Err(failed(err)),
+        };
+        let mut lines = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let Ok(record) = serde_json::from_str::<serde_json::Value>(&line.map_err(failed)?)
+            else {
+                continue;
+            };
+            let (Some(started), Some(ended)) =
+                (record["started"].as_str(), record["ended"].as_str())
+            else {
+                continue;
+            };
+            if record["phase"] != "work" || !started.starts_with(day) {
+                continue;
+            }
+            let mut line = format!("{}–{}", time_of(started), time_of(ended));
+            if let Some(task) = record["task"].as_str().filter(|_| !redact) {
+                line = format!("{} {}", line, task);
+            }
+            lines.push(format!(
+                "{} ({})",
+                line,
+                record["outcome"].as_str().unwrap_or("?")
+            ));
+            // Records written before the notes were kept have only their count.
+            for note in record["notes"].as_array().into_iter().flatten() {
+                let (Some(at), Some(text)) = (note["at"].as_str(), note["text"].as_str()) else {
+                    continue;
+                };
+                lines.push(format!("  {} {}", time_of(at), text));
+            }
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+/// `09:05` from a timestamp such as `2026-10-14T09:05:00+02:00` or `2026-10-14 09:05:00 +0200`.
+fn time_of(timestamp: &str) -> &str {
+    timestamp.get(11..16).unwrap_or(timestamp)
+}
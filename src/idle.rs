@@ -0,0 +1,105 @@
+use std::{thread, time::Duration};
+
+use crate::{
+    dbus::{Connection, Writer},
+    headless,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5); // How often the screensaver is asked
+const SCREENSAVER: &str = "org.freedesktop.ScreenSaver";
+const SCREENSAVER_PATH: &str = "/org/freedesktop/ScreenSaver";
+
+/// Watches `org.freedesktop.ScreenSaver` in the background and calls `changed` with `true` when
+/// the screen locks or the session has been idle for `after`, and with `false` once it is in use
+/// again. Desktops without the service are left to `ctl idle` and `ctl active`, and not polled.
+pub fn watch(after: Duration, mut changed: impl FnMut(bool) + Send + 'static) {
+    if headless::skip("idle", "watch the screensaver") {
+        return;
+    }
+    thread::spawn(move || {
+        let found = Connection::session()
+            .and_then(|mut connection| Ok(has_screensaver(&mut connection)?.then_some(connection)));
+        let mut bus = match found {
+            Ok(Some(connection)) => Some(connection),
+            Ok(None) => {
+                eprintln!(
+                    "No {} on the session bus, leaving idleness to ctl idle and ctl active",
+                    SCREENSAVER
+                );
+                return;
+            }
+            Err(err) => {
+                eprintln!("Cannot watch for idleness: {}", err);
+                return;
+            }
+        };
+        let mut idle = false;
+        let mut warned = false;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let result = match bus.as_mut() {
+                Some(connection) => query(connection, after),
+                None => Connection::session()
+                    .and_then(|connection| query(bus.insert(connection), after)),
+            };
+            match result {
+                Ok(now_idle) => {
+                    warned = false;
+                    if now_idle != idle {
+                        idle = now_idle;
+                        changed(idle);
+                    }
+                }
+                Err(err) => {
+                    // The bus may have restarted, so connect again next time.
+                    bus = None;
+                    if !std::mem::replace(&mut warned, true) {
+                        eprintln!("Cannot watch for idleness: {}", err);
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Whether some program on the bus provides the screensaver service.
+fn has_screensaver(bus: &mut Connection) -> Result<bool, String> {
+    let mut body = Writer::default();
+    body.string(SCREENSAVER);
+    let reply = bus.call(
+        "org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+        "NameHasOwner",
+        "s",
+        body.into_bytes(),
+    )?;
+    Ok(reply.body().u32()? != 0)
+}
+
+/// Whether the screen is locked or the session has been idle for `after`. Screensavers that
+/// do not track the idle time only report the lock.
+fn query(bus: &mut Connection, after: Duration) -> Result<bool, String> {
+    let active = bus.call(
+        SCREENSAVER,
+        SCREENSAVER_PATH,
+        SCREENSAVER,
+        "GetActive",
+        "",
+        Writer::default().into_bytes(),
+    )?;
+    if active.body().u32()? != 0 {
+        return Ok(true);
+    }
+    let idle_secs = bus
+        .call(
+            SCREENSAVER,
+            SCREENSAVER_PATH,
+            SCREENSAVER,
+            "GetSessionIdleTime",
+            "",
+            Writer::default().into_bytes(),
+        )
+        .and_then(|reply| reply.body().u32());
+    Ok(idle_secs.is_ok_and(|secs| u64::from(secs) >= after.as_secs()))
+}
@@ -0,0 +1,143 @@
+use std::{env, fs, path::PathBuf};
+
+const UNIT: &str = "waybar-pomodoro.service";
+const AUTOSTART_ENTRY: &str = "waybar-pomodoro.desktop";
+const HANDLER_ENTRY: &str = "waybar-pomodoro-handler.desktop";
+
+/// Writes the `pomodoro://` link handler entry and, with `autostart`, a systemd user unit and an
+/// XDG autostart entry that start the daemon with `daemon_args` at login, then says how to
+/// enable them.
+pub fn install(daemon_args: &[String], autostart: bool) -> Result<(), String> {
+    let exe = env::current_exe().map_err(|err| format!("Could not find the binary: {}", err))?;
+    let exe = exe.to_string_lossy().into_owned();
+    let data = dirs::data_dir().ok_or("No data directory: HOME is not set")?;
+
+    // Links go to the timer of the same namespace.
+    let mut handler = vec![exe.clone()];
+    if let Some(at) = daemon_args.iter().position(|arg| arg == "--namespace") {
+        handler.extend(daemon_args.iter().skip(at).take(2).cloned());
+    }
+    let mut written = vec![write(
+        data.join("applications").join(HANDLER_ENTRY),
+        &handler_entry(&handler),
+    )?];
+    if autostart {
+        let config = dirs::config_dir().ok_or("No config directory: HOME is not set")?;
+        let mut command = vec![exe];
+        command.extend_from_slice(daemon_args);
+        written.push(write(
+            config.join("systemd").join("user").join(UNIT),
+            &unit(&command),
+        )?);
+        written.push(write(
+            config.join("autostart").join(AUTOSTART_ENTRY),
+            &autostart_entry(&command),
+        )?);
+    }
+
+    for path in written {
+        println!("Wrote {}", path.display());
+    }
+    println!(
+        "Open pomodoro:// links with: xdg-mime default {} x-scheme-handler/pomodoro",
+        HANDLER_ENTRY
+    );
+    if autostart {
+        println!(
+            "Start at login with: systemctl --user enable --now {}",
+            UNIT
+        );
+    }
+    Ok(())
+}
+
+fn write(path: PathBuf, contents: &str) -> Result<PathBuf, String> {
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, contents))
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok(path)
+}
+
+fn handler_entry(command: &[String]) -> String {
+    let command: Vec<String> = command.iter().map(|arg| desktop_quote(arg)).collect();
+    format!(
+        "[Desktop Entry]
+Type=Application
+Name=Pomodoro link handler
+Comment=Start pomodoro:// links in waybar-pomodoro
+Exec={} uri %u
+MimeType=x-scheme-handler/pomodoro;
+NoDisplay=true
+Terminal=false
+",
+        command.join(" ")
+    )
+}
+
+/// Runs the daemon for the graphical session. A second copy started by the autostart entry exits
+/// right away, since the socket is taken.
+fn unit(command: &[String]) -> String {
+    let command: Vec<String> = command.iter().map(|arg| systemd_quote(arg)).collect();
+    format!(
+        "[Unit]
+Description=Pomodoro timer for waybar
+PartOf=graphical-session.target
+After=graphical-session.target
+
+[Service]
+ExecStart={}
+Restart=on-failure
+# The bar reads the status with `ctl status` rather than from the daemon's output.
+StandardOutput=null
+
+[Install]
+WantedBy=graphical-session.target
+",
+        command.join(" ")
+    )
+}
+
+/// For sessions without systemd.
+fn autostart_entry(command: &[String]) -> String {
+    let command: Vec<String> = command.iter().map(|arg| desktop_quote(arg)).collect();
+    format!(
+        "[Desktop Entry]
+Type=Application
+Name=Pomodoro timer
+Comment=Start the waybar-pomodoro daemon at login
+Exec={}
+NoDisplay=true
+Terminal=false
+",
+        command.join(" ")
+    )
+}
+
+/// Quotes an argument for an `Exec` key, following the desktop entry specification.
+fn desktop_quote(arg: &str) -> String {
+    let reserved = |c: char| c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c);
+    let arg = if arg.is_empty() || arg.contains(reserved) {
+        let mut quoted = String::from('"');
+        for c in arg.chars() {
+            if matches!(c, '"' | '`' | '$' | '\\') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    } else {
+        arg.to_string()
+    };
+    arg.replace('%', "%%")
+}
+
+/// Quotes an argument for `ExecStart`, escaping systemd's specifiers and variables.
+fn systemd_quote(arg: &str) -> String {
+    let arg = arg.replace('%', "%%").replace('$', "$$");
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || "\"'\\;".contains(c)) {
+        return arg;
+    }
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -1,87 +1,680 @@
 mod args;
-mod dunstify;
+mod audio;
+mod audit;
+mod bindings;
+mod clock;
+mod config;
+mod control;
+mod dbus;
+mod dispatch;
+mod errors;
+mod headless;
+mod history;
+mod idle;
+mod install;
+mod latency;
+mod metrics;
+mod mic;
+mod milestone;
+mod notify;
+mod output;
+mod plan;
 mod pomodoro;
+mod schedule;
+mod status;
+mod tasks;
+mod theme;
+mod uri;
+mod window;
 
-use dunstify::send_notification;
-use pomodoro::Pomodoro;
+use notify::{send_notification, PomodoroEvent, Sounds};
+use pomodoro::{Pomodoro, Reply};
 use std::{
-    fs::{File, OpenOptions},
-    io::{BufRead, BufReader},
-    os::unix::fs::OpenOptionsExt,
+    env,
+    ffi::CString,
+    fs,
+    os::unix::ffi::OsStringExt,
     path::Path,
+    process,
+    sync::mpsc::{self, RecvTimeoutError, Sender},
     sync::{Arc, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use crate::args::handle_args;
+use crate::args::{handle_args, Command};
+use crate::audit::Cause;
+use crate::clock::SuspendWatch;
+use crate::control::{Event, Request, Usage};
+use crate::dispatch::RateLimiter;
+use crate::metrics::TextfileExporter;
+use crate::output::Icons;
+use crate::schedule::AutoStart;
+use crate::status::{Privacy, StatusFd, StatusFile};
 
-const FIFO_PATH: &str = "pomodoro_fifo";
+const DISTRACTION_SAMPLE: Duration = Duration::from_secs(5); // Sampling for distractions alone
+const AUDIT_ENTRIES: usize = 20; // Transitions printed by a bare `audit`
 
 fn main() {
-    let sound_file = handle_args();
-    let pomodoro: Arc<Mutex<Pomodoro>> = Arc::new(Mutex::new(Pomodoro::new(sound_file)));
-    let command_queue = Arc::new(Mutex::new(Vec::<String>::new()));
+    let args = handle_args();
+    if args.headless {
+        headless::enable();
+    }
+    if let Command::Install {
+        autostart,
+        daemon_args,
+    } = &args.command
+    {
+        if let Err(err) = install::install(daemon_args, *autostart) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Command::SoundPreview(file) = &args.command {
+        if let Err(err) = audio::play(args.player, file, args.normalize, None) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut auto_start = AutoStart::new(&args.auto_start).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(2);
+    });
+
+    let namespace = match args.namespace.as_deref() {
+        Some("auto") => detect_namespace(),
+        namespace => namespace.map(|name| name.replace('/', "_")),
+    };
+    let socket_path = control::socket_path(namespace.as_deref());
+    if let Command::Ctl(command) = &args.command {
+        if let Err(err) = send_command(&socket_path, command) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        return;
+    }
+    if let Command::Bindings = args.command {
+        bindings::print(&control::ctl_command(namespace.as_deref()));
+        return;
+    }
+    if let Command::Uri(link) = &args.command {
+        let sent = uri::commands(link).and_then(|commands| {
+            commands
+                .iter()
+                .try_for_each(|command| send_command(&socket_path, command))
+        });
+        if let Err(err) = sent {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let mut status_file = if args.status_file {
+        let status_file = StatusFile::new(namespace.as_deref());
+        if status_file.is_none() {
+            eprintln!("Not mirroring the status: XDG_RUNTIME_DIR is not set");
+        }
+        status_file
+    } else {
+        None
+    };
+
+    let mut status_fd = args.status_fd.map(|fd| {
+        StatusFd::open(fd).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(2);
+        })
+    });
+
+    let focus_sample = match args.focus_sample {
+        None if !args.distractions.is_empty() => Some(DISTRACTION_SAMPLE),
+        focus_sample => focus_sample,
+    };
+    let mut builder = Pomodoro::builder();
+    if let Some(work) = args.work {
+        builder = builder.work(work);
+    }
+    if let Some(short_break) = args.short_break {
+        builder = builder.short_break(short_break);
+    }
+    if let Some(long_break) = args.long_break {
+        builder = builder.long_break(long_break);
+    }
+    if let Some(every) = args.long_break_every {
+        builder = builder.long_break_every(every);
+    }
+    let mut sounds = Sounds {
+        default: args.sound_file,
+        work: args.sound_work,
+        short_break: args.sound_break,
+        long_break: args.sound_long_break,
+    };
+    if let Err(err) = sounds.check_missing(args.missing_sound) {
+        eprintln!("{}", err);
+        process::exit(2);
+    }
+    if args.no_sound {
+        sounds = Sounds::default();
+    } else if sounds.default.is_none() {
+        match audio::bundled_chime() {
+            Ok(chime) => sounds.default = Some(chime.to_string_lossy().into_owned()),
+            Err(err) => eprintln!("Not playing the bundled chime: {}", err),
+        }
+    }
+    let pomodoro = builder
+        .sound(sounds, args.normalize, args.player)
+        .namespace(namespace)
+        .theme(args.theme)
+        .output_style(args.output_style, args.speak_at)
+        .format(args.format, args.format_text)
+        .icons(Icons::new(args.icons), !args.no_icons)
+        .auto_task(args.auto_task)
+        .focus_sampling(focus_sample)
+        .distractions(args.distractions, args.distraction_after)
+        .warmup(args.warmup)
+        .grace(args.grace)
+        .quiet_fullscreen(args.quiet_fullscreen)
+        .mic_probe(args.mic_probe)
+        .rollover_cap(args.rollover_cap)
+        .inbox(args.inbox)
+        .task_source(args.task_source)
+        .pause_reminder(args.pause_reminder)
+        .milestones(args.milestones, args.milestone_alert)
+        .break_countdown(args.break_countdown)
+        .phase_actions(args.phase_actions)
+        .auto_start_phases(args.auto_start_breaks, args.auto_start_work)
+        .overtime(args.overtime)
+        .privacy(args.privacy)
+        .on_suspend(args.on_suspend)
+        .daily_goal(args.daily_goal)
+        .build()
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        });
+    if let Command::Dump = args.command {
+        let mut pomodoro = pomodoro;
+        pomodoro.load_pomodoro_state();
+        println!("{}", serde_json::to_string_pretty(&pomodoro).unwrap());
+        return;
+    }
+    let listener = control::bind(&socket_path).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+    let pomodoro: Arc<Mutex<Pomodoro>> = Arc::new(Mutex::new(pomodoro));
     pomodoro.lock().unwrap().load_pomodoro_state();
-    if !Path::new(FIFO_PATH).exists() {
-        std::fs::remove_file(FIFO_PATH).ok();
-        nix::unistd::mkfifo(FIFO_PATH, nix::sys::stat::Mode::S_IRWXU).unwrap();
+    let (events, received) = mpsc::channel();
+    control::serve(listener, events.clone());
+
+    if let Some(after) = args.idle_pause {
+        let pomodoro = pomodoro.clone();
+        let events = events.clone();
+        idle::watch(after, move |idle| {
+            if pomodoro.lock().unwrap().set_idle(idle).is_some() {
+                let _ = events.send(Event::Wake);
+            }
+        });
     }
 
     let pomodoro_clone = pomodoro.clone();
-    println!("{}", pomodoro_clone.lock().unwrap().current_pomodoro());
-    let timer_thread = thread::spawn(move || loop {
-        let command = read_command(FIFO_PATH);
-        match command.as_str() {
-            "start" => pomodoro_clone.lock().unwrap().start(),
-            "pause" => pomodoro_clone.lock().unwrap().pause(),
-            "toggle" => {
+    let mut rate_limiter = RateLimiter::new(args.debounce, args.rate_limit);
+    let mut metrics = args.metrics_textfile.map(TextfileExporter::new);
+    let timer_thread = thread::spawn(move || {
+        let mut shown = String::new();
+        let mut next_tick = Instant::now();
+        let mut suspend_watch = SuspendWatch::new();
+        loop {
+            // Sleep until the output can change, waking early for commands and answered
+            // notifications. Commands are applied one at a time in the order they were sent,
+            // so none of them can race a transition that is still sending its notification.
+            let event = received.recv_timeout(next_tick.saturating_duration_since(Instant::now()));
+            // Deal with a suspend first, so a command sent on waking up sees the right time.
+            if let Some(slept) = suspend_watch.slept() {
+                pomodoro_clone.lock().unwrap().wake_from_suspend(slept);
+            }
+            match event {
+                Ok(Event::Command(request)) => {
+                    if !answer(&pomodoro_clone, &mut rate_limiter, request) {
+                        break;
+                    }
+                }
+                Ok(Event::Wake) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            // Notifications and sounds are sent in the background and report back here.
+            for failure in notify::take_failures() {
+                pomodoro_clone.lock().unwrap().report(failure);
+            }
+            if auto_start.due() {
+                start_automatically(&pomodoro_clone, &events);
+            }
+            let prompt = pomodoro_clone.lock().unwrap().take_prompt();
+            if let Some(event) = prompt {
+                prompt_next_phase(&pomodoro_clone, &events, event);
+            }
+            let suggestion = pomodoro_clone.lock().unwrap().take_task_suggestion();
+            if let Some(task) = suggestion {
+                offer_task(&pomodoro_clone, &events, task);
+            }
+            pomodoro_clone.lock().unwrap().sample_focus();
+            if let Some(metrics) = &mut metrics {
                 let mut pomodoro = pomodoro_clone.lock().unwrap();
-                if pomodoro.is_running {
-                    pomodoro.pause();
-                } else {
-                    pomodoro.start();
+                if let Err(failure) = metrics.tick(&pomodoro) {
+                    pomodoro.report(failure);
                 }
             }
-            "stop" => {
-                pomodoro_clone.lock().unwrap().pause();
-                break;
+            let status = pomodoro_clone.lock().unwrap().current_pomodoro();
+            if status != shown {
+                match &mut status_fd {
+                    Some(status_fd) => {
+                        if let Err(failure) = status_fd.write(&status) {
+                            pomodoro_clone.lock().unwrap().report(failure);
+                        }
+                    }
+                    None => println!("{}", status),
+                }
+                if let Some(status_file) = &mut status_file {
+                    if let Err(failure) = status_file.write(&status) {
+                        pomodoro_clone.lock().unwrap().report(failure);
+                    }
+                }
+                if let Some(signal) = args.signal {
+                    let mut pomodoro = pomodoro_clone.lock().unwrap();
+                    if let Err(failure) = status::signal_bar(pomodoro.bar_process(), signal) {
+                        pomodoro.report(failure);
+                    }
+                }
+                shown = status;
             }
-            _ => {}
+            next_tick = Instant::now() + pomodoro_clone.lock().unwrap().next_wake();
         }
-        println!("{}", pomodoro_clone.lock().unwrap().current_pomodoro());
-        thread::sleep(Duration::from_secs(1));
     });
 
-    let fifo = OpenOptions::new()
-        .read(true)
-        .custom_flags(libc::O_NONBLOCK)
-        .open(FIFO_PATH)
-        .unwrap();
+    timer_thread.join().unwrap();
+    let mut pomodoro = pomodoro.lock().unwrap();
+    if let Err(failure) = pomodoro.save_state() {
+        pomodoro.report(failure);
+    }
+    fs::remove_file(&socket_path).ok();
+}
 
-    let reader = BufReader::new(fifo);
+/// `queue add <task> [sessions]`, `queue list` and `queue clear`. The task keeps its case and may
+/// be quoted.
+fn handle_queue(pomodoro: &Mutex<Pomodoro>, args: &str) -> Handled {
+    let (action, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let mut pomodoro = pomodoro.lock().unwrap();
+    match action.to_lowercase().as_str() {
+        "add" => {
+            let rest = rest.trim();
+            let counted = rest
+                .rsplit_once(char::is_whitespace)
+                .and_then(|(task, count)| Some((task.trim(), count.parse::<u64>().ok()?)));
+            let (task, sessions) = counted.unwrap_or((rest, 1));
+            let task = task.trim_matches('"');
+            if task.is_empty() || sessions == 0 {
+                return Handled::Failed("Usage: queue add <task> [sessions]".to_string());
+            }
+            pomodoro.queue_task(task, sessions);
+            Handled::Done("queued".to_string())
+        }
+        "list" => Handled::Done(pomodoro.queued_tasks()),
+        "clear" => {
+            pomodoro.clear_queue();
+            Handled::Done("cleared".to_string())
+        }
+        _ => Handled::Failed(format!("Invalid command queue {}", args)),
+    }
+}
+
+/// Sends a command to a running timer and prints its reply, for key bindings and `on-click`
+/// handlers.
+fn send_command(socket_path: &Path, command: &str) -> Result<(), String> {
+    let (ok, reply) = control::send(socket_path, command)?;
+    if !ok {
+        return Err(reply);
+    }
+    if !reply.is_empty() {
+        println!("{}", reply);
+    }
+    Ok(())
+}
 
-    for line in reader.lines() {
-        let cmd = line.unwrap().to_lowercase();
-        if ["start", "pause", "toggle", "stop"].contains(&cmd.as_str()) {
-            command_queue.lock().unwrap().push(cmd);
-        } else {
-            println!("Invalid command");
+/// Applies a command and answers it. Returns `false` once the timer should stop.
+fn answer(pomodoro: &Mutex<Pomodoro>, rate_limiter: &mut RateLimiter, request: Request) -> bool {
+    let command = request.command.as_str();
+    // Only commands that change the timer can bounce; queries and reports from a bar or an
+    // idle daemon are always answered.
+    let changes_timer = matches!(control::usage(command), Some(Usage::Action | Usage::Adjust));
+    if changes_timer && !rate_limiter.allow(command) {
+        let status = pomodoro.lock().unwrap().snapshot();
+        request.respond(false, &format!("Dropped command {}", command), &status);
+        return true;
+    }
+    let handled = handle_command(pomodoro, command);
+    let (ok, reply) = match &handled {
+        Handled::Done(reply) => (true, reply.as_str()),
+        Handled::Failed(reply) => (false, reply.as_str()),
+        Handled::Upgrade => (true, "upgrading"),
+        Handled::Stop => (true, "stopping"),
+    };
+    // `status` is polled by the bar itself and would drown out the other replies.
+    // The replies to `done` and `queue list` name tasks.
+    let name = command.split(' ').next().unwrap_or_default();
+    if ["done", "queue"].contains(&name)
+        && pomodoro.lock().unwrap().privacy() == Privacy::RedactTasks
+    {
+        eprintln!("{}: reply redacted", name);
+    } else if command != "status" {
+        eprintln!("{}", reply);
+    }
+    request.respond(ok, reply, &pomodoro.lock().unwrap().snapshot());
+    match handled {
+        Handled::Upgrade => eprintln!("Could not upgrade: {}", upgrade()),
+        Handled::Stop => return false,
+        _ => {}
+    }
+    true
+}
+
+/// Suggests a task for the new session without blocking the timer while the user decides.
+fn offer_task(pomodoro: &Arc<Mutex<Pomodoro>>, events: &Sender<Event>, task: String) {
+    let pomodoro = pomodoro.clone();
+    let events = events.clone();
+    thread::spawn(move || {
+        match notify::offer_task(&task) {
+            Ok(true) => pomodoro.lock().unwrap().accept_task(task),
+            Ok(false) => {}
+            Err(failure) => pomodoro.lock().unwrap().report(failure),
+        }
+        let _ = events.send(Event::Wake);
+    });
+}
+
+/// Shows the notification for the next phase with its actions and applies the one picked.
+fn prompt_next_phase(
+    pomodoro: &Arc<Mutex<Pomodoro>>,
+    events: &Sender<Event>,
+    event: PomodoroEvent,
+) {
+    let pomodoro = pomodoro.clone();
+    let events = events.clone();
+    thread::spawn(move || {
+        match notify::prompt_next_phase(event) {
+            Ok(Some(choice)) => pomodoro.lock().unwrap().answer_prompt(event, choice),
+            Ok(None) => {}
+            Err(failure) => pomodoro.lock().unwrap().report(failure),
         }
+        let _ = events.send(Event::Wake);
+    });
+}
+
+/// Replaces the process with the binary now installed at its path, run with the same
+/// arguments, so it picks the state just saved up again and keeps writing to the bar.
+/// Only returns if that fails.
+fn upgrade() -> String {
+    let path = match env::current_exe() {
+        // After a package update the running binary is gone and its path says so.
+        Ok(path) => path
+            .to_string_lossy()
+            .trim_end_matches(" (deleted)")
+            .to_string(),
+        Err(err) => return err.to_string(),
+    };
+    let Ok(path) = CString::new(path) else {
+        return "Invalid executable path".to_string();
+    };
+    let Ok(args) = env::args_os()
+        .map(|arg| CString::new(arg.into_vec()))
+        .collect::<Result<Vec<_>, _>>()
+    else {
+        return "Invalid arguments".to_string();
+    };
+    match nix::unistd::execv(&path, &args) {
+        Ok(never) => match never {},
+        Err(err) => err.to_string(),
     }
+}
 
-    timer_thread.join().unwrap();
-    pomodoro.lock().unwrap().save_state();
+/// Names the namespace after the enclosing git repository, or the current directory.
+fn detect_namespace() -> Option<String> {
+    let toplevel = process::Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    let dir = match toplevel {
+        Some(toplevel) => toplevel.into(),
+        None => std::env::current_dir().ok()?,
+    };
+    dir.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
 }
 
-fn read_command(command_path: &str) -> String {
-    if let Ok(file) = File::open(command_path) {
-        let mut buf_reader = BufReader::new(file);
-        let mut command = String::new();
-        buf_reader.read_line(&mut command).unwrap();
-        std::fs::remove_file(command_path).ok();
-        command.trim().to_lowercase()
-    } else {
-        String::new()
+/// Starts a session on schedule, offering a one-click cancel in the notification.
+fn start_automatically(pomodoro: &Arc<Mutex<Pomodoro>>, events: &Sender<Event>) {
+    if pomodoro.lock().unwrap().in_grace() {
+        eprintln!("Not starting automatically during the grace period after a break");
+        return;
+    }
+    if pomodoro.lock().unwrap().start(Cause::AutoStart) == Reply::AlreadyRunning {
+        return;
+    }
+    let pomodoro = pomodoro.clone();
+    let events = events.clone();
+    thread::spawn(move || {
+        match notify::notify_auto_start() {
+            Ok(true) => pomodoro.lock().unwrap().cancel_phase(Cause::Notification),
+            Ok(false) => {}
+            Err(failure) => pomodoro.lock().unwrap().report(failure),
+        }
+        let _ = events.send(Event::Wake);
+    });
+}
+
+/// What became of a command.
+enum Handled {
+    Done(String),   // Applied, with the reply for the client
+    Failed(String), // Refused, e.g. for a malformed argument
+    Upgrade,        // The process is to be replaced once the client has its reply
+    Stop,
+}
+
+/// Applies one command from the socket.
+fn handle_command(pomodoro: &Mutex<Pomodoro>, command: &str) -> Handled {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    match words.as_slice() {
+        ["start", "--in", delay] => match clock::parse_duration(delay) {
+            Some(delay) => {
+                let mut pomodoro = pomodoro.lock().unwrap();
+                let reply = pomodoro.schedule_start(delay, Cause::Command);
+                Handled::Done(pomodoro.describe(reply))
+            }
+            None => Handled::Failed(format!("Invalid delay {}", delay)),
+        },
+        ["start", "--at", time] => match clock::until_local_time(time) {
+            Some(delay) => {
+                let mut pomodoro = pomodoro.lock().unwrap();
+                let reply = pomodoro.schedule_start(delay, Cause::Command);
+                Handled::Done(pomodoro.describe(reply))
+            }
+            None => Handled::Failed(format!("Invalid time {}", time)),
+        },
+        ["start", "--for", length] => match clock::parse_duration(length) {
+            Some(length) if !length.is_zero() => {
+                let mut pomodoro = pomodoro.lock().unwrap();
+                let reply = pomodoro.start_for(length, Cause::Command);
+                Handled::Done(pomodoro.describe(reply))
+            }
+            _ => Handled::Failed(format!("Invalid length {}", length)),
+        },
+        [name @ ("start" | "pause" | "toggle")] => {
+            let mut pomodoro = pomodoro.lock().unwrap();
+            let reply = match *name {
+                "start" => pomodoro.start(Cause::Command),
+                "pause" => pomodoro.pause(Cause::Command),
+                _ if pomodoro.is_running => pomodoro.pause(Cause::Command),
+                _ => pomodoro.start(Cause::Command),
+            };
+            Handled::Done(pomodoro.describe(reply))
+        }
+        [name @ ("idle" | "active")] => {
+            let mut pomodoro = pomodoro.lock().unwrap();
+            match pomodoro.set_idle(*name == "idle") {
+                Some(reply) => Handled::Done(pomodoro.describe(reply)),
+                None => Handled::Done("unchanged".to_string()),
+            }
+        }
+        [name @ ("skip" | "reset")] => {
+            let mut pomodoro = pomodoro.lock().unwrap();
+            let reply = match *name {
+                "skip" => pomodoro.skip(Cause::Command),
+                _ => pomodoro.reset(Cause::Command),
+            };
+            Handled::Done(pomodoro.describe(reply))
+        }
+        [name @ ("add" | "sub"), amount] => {
+            let seconds = clock::parse_duration(amount)
+                .and_then(|amount| i64::try_from(amount.as_secs()).ok());
+            let mut pomodoro = pomodoro.lock().unwrap();
+            let reply = seconds.and_then(|seconds| match *name {
+                "add" => pomodoro.adjust(seconds),
+                _ => pomodoro.adjust(-seconds),
+            });
+            match reply {
+                Some(reply) => Handled::Done(pomodoro.describe(reply)),
+                None => Handled::Failed(format!("Invalid amount {}", amount)),
+            }
+        }
+        ["note", _, ..] => {
+            let text = command["note".len()..].trim();
+            if pomodoro.lock().unwrap().add_note(text) {
+                Handled::Done("noted".to_string())
+            } else {
+                Handled::Failed("No work session to attach the note to".to_string())
+            }
+        }
+        ["capture", _, ..] => {
+            let text = command["capture".len()..].trim();
+            let mut pomodoro = pomodoro.lock().unwrap();
+            match pomodoro.capture(text) {
+                Ok(()) => Handled::Done("captured".to_string()),
+                Err(failure) => {
+                    let reply = format!("{}: {}", failure.code, failure.message);
+                    pomodoro.report(failure);
+                    Handled::Failed(reply)
+                }
+            }
+        }
+        ["task", _, ..] => {
+            let text = command["task".len()..].trim();
+            if pomodoro.lock().unwrap().set_task(text) {
+                Handled::Done("labelled".to_string())
+            } else {
+                Handled::Failed("Tasks label work sessions, not breaks".to_string())
+            }
+        }
+        ["done"] => match pomodoro.lock().unwrap().complete_task() {
+            Ok(reply) => Handled::Done(reply),
+            Err(err) => Handled::Failed(err),
+        },
+        ["queue", ..] => handle_queue(pomodoro, command["queue".len()..].trim()),
+        ["status"] => Handled::Done(pomodoro.lock().unwrap().current_pomodoro()),
+        ["errors"] => Handled::Done(pomodoro.lock().unwrap().recent_errors()),
+        ["stats"] => Handled::Done(pomodoro.lock().unwrap().stats()),
+        ["report"] => match pomodoro.lock().unwrap().daily_report() {
+            Ok(report) => Handled::Done(report),
+            Err(failure) => Handled::Failed(failure.message),
+        },
+        ["audit"] => Handled::Done(pomodoro.lock().unwrap().recent_transitions(AUDIT_ENTRIES)),
+        ["audit", "--last", count] => match count.parse() {
+            Ok(count) => Handled::Done(pomodoro.lock().unwrap().recent_transitions(count)),
+            Err(_) => Handled::Failed(format!("Invalid count {}", count)),
+        },
+        ["dump"] => {
+            let pomodoro = pomodoro.lock().unwrap();
+            Handled::Done(serde_json::to_string_pretty(&*pomodoro).unwrap())
+        }
+        // Without the state the new binary would start over, so stay on the old one.
+        ["upgrade"] => match pomodoro.lock().unwrap().save_state() {
+            Ok(()) => Handled::Upgrade,
+            Err(failure) => Handled::Failed(failure.message),
+        },
+        ["stop"] => {
+            pomodoro.lock().unwrap().pause(Cause::Command);
+            Handled::Stop
+        }
+        _ => Handled::Failed(format!("Invalid command {}", command)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pomodoro::tests::timer;
+
+    /// Runs the commands on the queue the way the timer thread does, one at a time in the
+    /// order they arrived.
+    fn apply_queued(pomodoro: &Mutex<Pomodoro>, received: &mpsc::Receiver<Event>) {
+        let mut rate_limiter = RateLimiter::new(Duration::ZERO, 100);
+        while let Ok(Event::Command(request)) = received.try_recv() {
+            answer(pomodoro, &mut rate_limiter, request);
+        }
+    }
+
+    fn response(reply: &mpsc::Receiver<String>) -> String {
+        let response: serde_json::Value = serde_json::from_str(&reply.recv().unwrap()).unwrap();
+        response["reply"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn commands_sent_during_a_transition_apply_in_arrival_order() {
+        let _local_time = clock::tests::lock_local_time();
+        let pomodoro = Arc::new(Mutex::new(timer(25, 5, 15)));
+        let (events, received) = mpsc::channel();
+        // A transition holds the timer, e.g. while its notification goes out.
+        let transition = pomodoro.lock().unwrap();
+        let replies: Vec<_> = ["toggle", "add 1m", "skip"]
+            .into_iter()
+            .map(|command| {
+                let (request, reply) = Request::new(command.to_string());
+                events.send(Event::Command(request)).unwrap();
+                reply
+            })
+            .collect();
+        drop(transition);
+        apply_queued(&pomodoro, &received);
+
+        assert!(response(&replies[0]).starts_with("started phase=work"));
+        // Added to the running work session, before the skip ended it.
+        assert!(response(&replies[1]).contains("phase=work remaining=25:"));
+        assert!(response(&replies[2]).starts_with("skipped phase=short-break"));
+        assert_eq!(pomodoro.lock().unwrap().snapshot().phase, "short-break");
+    }
+
+    #[test]
+    fn a_toggle_queued_twice_starts_and_pauses() {
+        let _local_time = clock::tests::lock_local_time();
+        let pomodoro = Mutex::new(timer(25, 5, 15));
+        let (events, received) = mpsc::channel();
+        let replies: Vec<_> = ["toggle", "toggle"]
+            .into_iter()
+            .map(|command| {
+                let (request, reply) = Request::new(command.to_string());
+                events.send(Event::Command(request)).unwrap();
+                reply
+            })
+            .collect();
+        apply_queued(&pomodoro, &received);
+
+        assert!(response(&replies[0]).starts_with("started"));
+        assert!(response(&replies[1]).starts_with("paused"));
+        assert!(!pomodoro.lock().unwrap().is_running);
     }
 }
@@ -0,0 +1,160 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    time::{Duration, Instant},
+};
+
+use crate::{errors::Failure, latency::INTEGRATIONS, pomodoro::Pomodoro};
+
+const WRITE_INTERVAL: Duration = Duration::from_secs(60); // How often the textfile is refreshed
+const PHASES: [&str; 3] = ["work", "short-break", "long-break"];
+
+/// Writes the timer's metrics for node_exporter's textfile collector.
+pub struct TextfileExporter {
+    path: String,
+    last_written: Option<Instant>,
+}
+
+impl TextfileExporter {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            last_written: None,
+        }
+    }
+
+    /// Rewrites the textfile once the write interval has passed.
+    pub fn tick(&mut self, pomodoro: &Pomodoro) -> Result<(), Failure> {
+        if self
+            .last_written
+            .is_some_and(|written| written.elapsed() < WRITE_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_written = Some(Instant::now());
+
+        // The collector may read at any time, so replace the file in one step.
+        let temporary = format!("{}.tmp", self.path);
+        fs::write(&temporary, render(&pomodoro.dump()))
+            .and_then(|_| fs::rename(&temporary, &self.path))
+            .map_err(|err| Failure::new("metrics", format!("{}: {}", self.path, err)))
+    }
+}
+
+/// Renders a state snapshot from [`Pomodoro::dump`] in the Prometheus text format.
+fn render(state: &serde_json::Value) -> String {
+    let labels = match state["config"]["namespace"].as_str() {
+        Some(namespace) => format!("namespace=\"{}\"", escape(namespace)),
+        None => String::new(),
+    };
+    let with = |extra: &str| match (labels.is_empty(), extra.is_empty()) {
+        (true, true) => String::new(),
+        (true, false) => format!("{{{}}}", extra),
+        (false, true) => format!("{{{}}}", labels),
+        (false, false) => format!("{{{},{}}}", labels, extra),
+    };
+
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, u64)>| {
+        let _ = writeln!(out, "# HELP waybar_pomodoro_{} {}", name, help);
+        let _ = writeln!(out, "# TYPE waybar_pomodoro_{} {}", name, kind);
+        for (labels, value) in samples {
+            let _ = writeln!(out, "waybar_pomodoro_{}{} {}", name, labels, value);
+        }
+    };
+
+    let status = &state["status"];
+    let phase = status["phase"].as_str().unwrap_or_default();
+    metric(
+        "phase",
+        "gauge",
+        "Whether the timer is in the given phase.",
+        PHASES
+            .iter()
+            .map(|name| {
+                let labels = with(&format!("phase=\"{}\"", name));
+                (labels, u64::from(phase == *name))
+            })
+            .collect(),
+    );
+    metric(
+        "running",
+        "gauge",
+        "Whether the timer is running.",
+        vec![(with(""), u64::from(status["running"] == true))],
+    );
+    metric(
+        "remaining_seconds",
+        "gauge",
+        "Time left in the current phase.",
+        vec![(with(""), status["remaining_secs"].as_u64().unwrap_or(0))],
+    );
+    metric(
+        "pomodoros_completed",
+        "gauge",
+        "Pomodoros completed since the last long break.",
+        vec![(with(""), status["cycle"].as_u64().unwrap_or(0))],
+    );
+    metric(
+        "breaks_total",
+        "counter",
+        "Breaks by how they were used.",
+        ["taken", "shortened", "skipped"]
+            .iter()
+            .map(|outcome| {
+                let labels = with(&format!("outcome=\"{}\"", outcome));
+                (labels, state["breaks"][outcome].as_u64().unwrap_or(0))
+            })
+            .collect(),
+    );
+    let latency = |field: &str| -> Vec<(String, u64)> {
+        INTEGRATIONS
+            .iter()
+            .map(|(name, ..)| {
+                let labels = with(&format!("integration=\"{}\"", name));
+                (labels, state["latency"][name][field].as_u64().unwrap_or(0))
+            })
+            .collect()
+    };
+    metric(
+        "integration_calls_total",
+        "counter",
+        "Notifications sent and sounds played.",
+        latency("calls"),
+    );
+    metric(
+        "integration_failures_total",
+        "counter",
+        "Notifications and sounds that failed.",
+        latency("failures"),
+    );
+    metric(
+        "integration_milliseconds_total",
+        "counter",
+        "Time spent sending notifications and playing sounds.",
+        latency("total_ms"),
+    );
+    metric(
+        "integration_slowest_milliseconds",
+        "gauge",
+        "The longest single notification or sound.",
+        latency("slowest_ms"),
+    );
+    metric(
+        "recent_errors",
+        "gauge",
+        "Failures kept for the errors command.",
+        vec![(
+            with(""),
+            state["recent_errors"].as_array().map_or(0, Vec::len) as u64,
+        )],
+    );
+    out
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,68 @@
+use std::{fs, process::Command, str::FromStr};
+
+use serde_json::Value;
+
+/// How to tell whether the microphone is in use, e.g. by a call.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MicProbe {
+    Pipewire, // A running audio input stream in `pw-dump`
+    Proc,     // A running ALSA capture device under /proc/asound
+}
+
+impl FromStr for MicProbe {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "pipewire" => Ok(MicProbe::Pipewire),
+            "proc" => Ok(MicProbe::Proc),
+            _ => Err(format!("Unknown microphone probe {}", name)),
+        }
+    }
+}
+
+impl MicProbe {
+    /// Whether something is recording right now. Probes that cannot run report `false`.
+    pub fn in_use(self) -> bool {
+        match self {
+            MicProbe::Pipewire => pipewire_capturing(),
+            MicProbe::Proc => alsa_capturing(),
+        }
+    }
+}
+
+fn pipewire_capturing() -> bool {
+    let Ok(output) = Command::new("pw-dump").output() else {
+        return false;
+    };
+    let Ok(objects) = serde_json::from_slice::<Value>(&output.stdout) else {
+        return false;
+    };
+    objects.as_array().into_iter().flatten().any(|object| {
+        object["type"] == "PipeWire:Interface:Node"
+            && object["info"]["props"]["media.class"] == "Stream/Input/Audio"
+            && object["info"]["state"] == "running"
+    })
+}
+
+/// Looks for `state: RUNNING` in the status of any capture substream.
+fn alsa_capturing() -> bool {
+    let Ok(cards) = fs::read_dir("/proc/asound") else {
+        return false;
+    };
+    cards
+        .flatten()
+        .filter_map(|card| fs::read_dir(card.path()).ok())
+        .flatten()
+        .flatten()
+        .filter(|pcm| {
+            let name = pcm.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("pcm") && name.ends_with('c')
+        })
+        .filter_map(|pcm| fs::read_dir(pcm.path()).ok())
+        .flatten()
+        .flatten()
+        .filter_map(|substream| fs::read_to_string(substream.path().join("status")).ok())
+        .any(|status| status.contains("state: RUNNING"))
+}
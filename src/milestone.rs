@@ -0,0 +1,73 @@
+use std::{str::FromStr, time::Duration};
+
+/// A point in a work session that can be announced on its own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Milestone {
+    Half,       // Half of the session has passed
+    LastFive,   // Five minutes remain
+    LastMinute, // One minute remains
+}
+
+impl FromStr for Milestone {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "half" => Ok(Milestone::Half),
+            "last-5m" => Ok(Milestone::LastFive),
+            "last-1m" => Ok(Milestone::LastMinute),
+            _ => Err(format!("Unknown milestone {}", name)),
+        }
+    }
+}
+
+impl Milestone {
+    /// The remaining time at which a session of `length` reaches the milestone.
+    pub fn remaining_at(self, length: Duration) -> Duration {
+        match self {
+            Milestone::Half => length / 2,
+            Milestone::LastFive => Duration::from_secs(5 * 60),
+            Milestone::LastMinute => Duration::from_secs(60),
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            Milestone::Half => "Halfway through the session.",
+            Milestone::LastFive => "5 minutes left in the session.",
+            Milestone::LastMinute => "1 minute left in the session.",
+        }
+    }
+}
+
+/// How milestones are announced.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MilestoneAlert {
+    #[default]
+    Notify, // A notification without sound
+    Sound, // The notification sound alone
+    Both,  // A notification with its sound
+}
+
+impl FromStr for MilestoneAlert {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "notify" => Ok(MilestoneAlert::Notify),
+            "sound" => Ok(MilestoneAlert::Sound),
+            "both" => Ok(MilestoneAlert::Both),
+            _ => Err(format!("Unknown milestone alert {}", name)),
+        }
+    }
+}
+
+impl MilestoneAlert {
+    pub fn notifies(self) -> bool {
+        self != MilestoneAlert::Sound
+    }
+
+    pub fn plays_sound(self) -> bool {
+        self != MilestoneAlert::Notify
+    }
+}
@@ -0,0 +1,433 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{mpsc, Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    audio::{self, MissingSound, Player},
+    dbus::{Connection, Writer},
+    errors::Failure,
+    headless, latency,
+    pomodoro::scratch_file,
+};
+
+const APP_NAME: &str = "waybar-pomodoro";
+const DESTINATION: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const INTERFACE: &str = "org.freedesktop.Notifications";
+const DEFAULT_TIMEOUT: i32 = -1; // Let the notification daemon decide
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(10 * 60); // How long an action is awaited
+const ICONS: &[(&str, &str)] = &[
+    ("tomato", include_str!("../assets/icons/tomato.svg")),
+    ("coffee", include_str!("../assets/icons/coffee.svg")),
+    ("rest", include_str!("../assets/icons/rest.svg")),
+];
+
+#[derive(Clone, Copy, Debug)]
+pub enum PomodoroEvent {
+    Pomodoro,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PomodoroEvent {
+    pub fn message(self) -> &'static str {
+        match self {
+            PomodoroEvent::Pomodoro => "Time for a Pomodoro session!",
+            PomodoroEvent::ShortBreak => "Take a short break.",
+            PomodoroEvent::LongBreak => "Take a long break.",
+        }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            PomodoroEvent::Pomodoro => "tomato",
+            PomodoroEvent::ShortBreak => "coffee",
+            PomodoroEvent::LongBreak => "rest",
+        }
+    }
+
+    /// The name the event is saved under in the state file.
+    pub fn name(self) -> &'static str {
+        match self {
+            PomodoroEvent::Pomodoro => "pomodoro",
+            PomodoroEvent::ShortBreak => "short-break",
+            PomodoroEvent::LongBreak => "long-break",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pomodoro" => Some(PomodoroEvent::Pomodoro),
+            "short-break" => Some(PomodoroEvent::ShortBreak),
+            "long-break" => Some(PomodoroEvent::LongBreak),
+            _ => None,
+        }
+    }
+}
+
+/// Sound files played with the notifications, by event.
+#[derive(Clone, Debug, Default)]
+pub struct Sounds {
+    pub default: Option<String>, // Played for events without a sound of their own
+    pub work: Option<String>,
+    pub short_break: Option<String>,
+    pub long_break: Option<String>, // Falls back to the short break sound
+}
+
+impl Sounds {
+    pub fn for_event(&self, event: PomodoroEvent) -> Option<&str> {
+        let own = match event {
+            PomodoroEvent::Pomodoro => self.work.as_ref(),
+            PomodoroEvent::ShortBreak => self.short_break.as_ref(),
+            PomodoroEvent::LongBreak => self.long_break.as_ref().or(self.short_break.as_ref()),
+        };
+        own.or(self.default.as_ref()).map(String::as_str)
+    }
+
+    /// Deals with the sound files that do not exist as `missing` says, once at startup.
+    pub fn check_missing(&mut self, missing: MissingSound) -> Result<(), String> {
+        let mut chime = None;
+        let sounds = [
+            &mut self.default,
+            &mut self.work,
+            &mut self.short_break,
+            &mut self.long_break,
+        ];
+        for sound in sounds {
+            let Some(sound_file) = sound.take_if(|file| !Path::new(file).is_file()) else {
+                continue;
+            };
+            match missing {
+                MissingSound::Error => {
+                    return Err(format!("Sound file {} does not exist", sound_file))
+                }
+                MissingSound::Warn => {
+                    eprintln!("Sound file {} does not exist, ignoring it", sound_file)
+                }
+                MissingSound::Chime => {
+                    eprintln!(
+                        "Sound file {} does not exist, playing the bundled chime",
+                        sound_file
+                    );
+                    if chime.is_none() {
+                        chime = Some(audio::bundled_chime()?.to_string_lossy().into_owned());
+                    }
+                    *sound = chime.clone();
+                }
+                MissingSound::Off => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Notifies about the event, playing its sound if it has one.
+pub fn send_notification(
+    event: PomodoroEvent,
+    sounds: &Sounds,
+    normalize: bool,
+    namespace: Option<&str>,
+    player: Player,
+) -> Result<(), Failure> {
+    send_message(event.icon(), event.message());
+    if let Some(sound_file) = sounds.for_event(event) {
+        play_chime(player, sound_file, normalize, namespace)?;
+    }
+    Ok(())
+}
+
+/// Plays the notification sound on its own.
+pub fn play_chime(
+    player: Player,
+    sound_file: &str,
+    normalize: bool,
+    namespace: Option<&str>,
+) -> Result<(), Failure> {
+    if !Path::new(sound_file).exists() {
+        return Err(Failure::new(
+            "sound-missing",
+            format!("Sound file {} does not exist", sound_file),
+        ));
+    }
+    if headless::skip("sound", sound_file) {
+        return Ok(());
+    }
+    queue(Job::Chime {
+        player,
+        sound_file: sound_file.to_string(),
+        normalize,
+        namespace: namespace.map(str::to_string),
+    });
+    Ok(())
+}
+
+/// Shows a plain notification without any sound.
+pub fn send_message(icon: &str, message: &str) {
+    if headless::skip("notify", message) {
+        return;
+    }
+    queue(Job::Message {
+        icon: icon.to_string(),
+        message: message.to_string(),
+    });
+}
+
+/// Shows the break countdown in place of the one shown last, or as a new notification. It
+/// stays until closed.
+pub fn show_countdown(message: &str) {
+    if headless::skip("notify", message) {
+        return;
+    }
+    queue(Job::Countdown(message.to_string()));
+}
+
+/// Closes the break countdown notification.
+pub fn close_countdown() {
+    if headless::skip("notify", "close the break countdown") {
+        return;
+    }
+    queue(Job::CloseCountdown);
+}
+
+/// Takes the failures of notifications and sounds sent since the last call, oldest first.
+pub fn take_failures() -> Vec<Failure> {
+    std::mem::take(&mut *FAILURES.lock().unwrap())
+}
+
+/// Notifications and sounds waiting for the sender thread.
+enum Job {
+    Message {
+        icon: String,
+        message: String,
+    },
+    Chime {
+        player: Player,
+        sound_file: String,
+        normalize: bool,
+        namespace: Option<String>,
+    },
+    Countdown(String),
+    CloseCountdown,
+}
+
+/// Failures of queued jobs, until the timer picks them up for its error log.
+static FAILURES: Mutex<Vec<Failure>> = Mutex::new(Vec::new());
+
+/// Hands a job to the sender thread, started on first use. Jobs run one at a time in the
+/// order they were queued, but nothing waits for them, so a notification daemon that hangs
+/// or a long chime cannot hold up the timer and its commands.
+fn queue(job: Job) {
+    static JOBS: OnceLock<mpsc::Sender<Job>> = OnceLock::new();
+    let jobs = JOBS.get_or_init(|| {
+        let (jobs, received) = mpsc::channel();
+        thread::spawn(move || {
+            let mut countdown = 0; // Notification showing the break countdown, 0 for none
+            for job in received {
+                if let Err(failure) = run(job, &mut countdown) {
+                    FAILURES.lock().unwrap().push(failure);
+                }
+            }
+        });
+        jobs
+    });
+    // The thread only stops with the process.
+    let _ = jobs.send(job);
+}
+
+fn run(job: Job, countdown: &mut u32) -> Result<(), Failure> {
+    match job {
+        Job::Message { icon, message } => latency::measure("notify", || {
+            let mut bus = connect()?;
+            notify(&mut bus, &icon, &message, &[], 0, DEFAULT_TIMEOUT).map(|_| ())
+        }),
+        Job::Chime {
+            player,
+            sound_file,
+            normalize,
+            namespace,
+        } => latency::measure("sound", || {
+            audio::play(player, &sound_file, normalize, namespace.as_deref())
+        })
+        .map_err(|err| Failure::new("sound", err)),
+        Job::Countdown(message) => {
+            *countdown = latency::measure("notify", || {
+                let mut bus = connect()?;
+                notify(&mut bus, "coffee", &message, &[], *countdown, 0)
+            })?;
+            Ok(())
+        }
+        Job::CloseCountdown => {
+            let id = std::mem::take(countdown);
+            if id == 0 {
+                return Ok(());
+            }
+            latency::measure("notify", || close(id))
+        }
+    }
+}
+
+fn close(id: u32) -> Result<(), Failure> {
+    let mut body = Writer::default();
+    body.u32(id);
+    connect()?
+        .call(
+            DESTINATION,
+            PATH,
+            INTERFACE,
+            "CloseNotification",
+            "u",
+            body.into_bytes(),
+        )
+        .map(|_| ())
+        .map_err(failed)
+}
+
+/// Tells the user a session was started automatically and waits for them to act on it.
+/// Returns true if they chose to cancel the session.
+pub fn notify_auto_start() -> Result<bool, Failure> {
+    ask("Pomodoro started automatically.", "cancel", "Cancel")
+}
+
+/// Suggests a task for the session that just started. Returns true if the user took it.
+pub fn offer_task(task: &str) -> Result<bool, Failure> {
+    ask(&format!("Next up: {}", task), "accept", "Work on this")
+}
+
+/// What the user picked on the notification for a phase that ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PhaseChoice {
+    Start,  // Start the next phase now
+    Snooze, // Start it in a few minutes
+    Skip,   // Skip it and set up the one after
+}
+
+/// Announces the phase that comes next with *Start*, *Snooze 5 min* and *Skip* actions,
+/// and waits until one is picked or the notification is closed.
+pub fn prompt_next_phase(event: PomodoroEvent) -> Result<Option<PhaseChoice>, Failure> {
+    let start = match event {
+        PomodoroEvent::Pomodoro => "Start work",
+        PomodoroEvent::ShortBreak | PomodoroEvent::LongBreak => "Start break",
+    };
+    let actions = ["start", start, "snooze", "Snooze 5 min", "skip", "Skip"];
+    let choice = choose(event.icon(), event.message(), &actions)?;
+    Ok(match choice.as_deref() {
+        Some("start") => Some(PhaseChoice::Start),
+        Some("snooze") => Some(PhaseChoice::Snooze),
+        Some("skip") => Some(PhaseChoice::Skip),
+        _ => None,
+    })
+}
+
+/// Shows a notification with one action and waits until it is closed.
+/// Returns true if the action was picked.
+fn ask(message: &str, action: &str, label: &str) -> Result<bool, Failure> {
+    let choice = choose("tomato", message, &[action, label])?;
+    Ok(choice.as_deref() == Some(action))
+}
+
+/// Shows a notification with actions, given as key and label pairs, and waits until it is
+/// closed. Returns the key of the action picked, if any.
+fn choose(icon: &str, message: &str, actions: &[&str]) -> Result<Option<String>, Failure> {
+    // Nobody is there to pick an action, so the notification counts as closed.
+    if headless::skip("notify", message) {
+        return Ok(None);
+    }
+    let mut bus = connect()?;
+    for member in ["ActionInvoked", "NotificationClosed"] {
+        let mut rule = Writer::default();
+        rule.string(&format!(
+            "type='signal',interface='{}',member='{}'",
+            INTERFACE, member
+        ));
+        bus.call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "AddMatch",
+            "s",
+            rule.into_bytes(),
+        )
+        .map_err(failed)?;
+    }
+    let id = notify(&mut bus, icon, message, actions, 0, 0)?;
+    let deadline = Instant::now() + PROMPT_TIMEOUT;
+    loop {
+        let Some(signal) = bus.next_signal(deadline).map_err(failed)? else {
+            // The daemon never said what became of it, so take it down and stop waiting.
+            close(id)?;
+            return Ok(None);
+        };
+        let mut body = signal.body();
+        if body.u32().map_err(failed)? != id {
+            continue;
+        }
+        match signal.member.as_deref() {
+            Some("ActionInvoked") => return Ok(Some(body.string().map_err(failed)?)),
+            Some("NotificationClosed") => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+/// The path of the icon built into the binary for `name`, written out on first use, since icon
+/// themes rarely have a tomato. Other names, and icons that cannot be written, are passed to
+/// the notification daemon as they are.
+fn bundled_icon(name: &str) -> String {
+    let Some((_, svg)) = ICONS.iter().find(|(icon, _)| *icon == name) else {
+        return name.to_string();
+    };
+    let Ok(path) = scratch_file(&format!("{}.svg", name)) else {
+        return name.to_string();
+    };
+    let current = fs::read_to_string(&path).is_ok_and(|written| written == *svg);
+    if current || fs::write(&path, svg).is_ok() {
+        path.to_string_lossy().into_owned()
+    } else {
+        name.to_string()
+    }
+}
+
+fn connect() -> Result<Connection, Failure> {
+    Connection::session().map_err(failed)
+}
+
+fn failed(err: String) -> Failure {
+    Failure::new("notify", format!("Failed to send notification: {}", err))
+}
+
+/// Calls `Notify` on the notification daemon and returns the notification's id.
+fn notify(
+    bus: &mut Connection,
+    icon: &str,
+    summary: &str,
+    actions: &[&str],
+    replaces: u32,
+    timeout_ms: i32,
+) -> Result<u32, Failure> {
+    let mut body = Writer::default();
+    body.string(APP_NAME);
+    body.u32(replaces);
+    body.string(&bundled_icon(icon));
+    body.string(summary);
+    body.string("");
+    body.array(4, |body| {
+        actions.iter().for_each(|action| body.string(action))
+    });
+    body.array(8, |_| {});
+    body.i32(timeout_ms);
+    let reply = bus
+        .call(
+            DESTINATION,
+            PATH,
+            INTERFACE,
+            "Notify",
+            "susssasa{sv}i",
+            body.into_bytes(),
+        )
+        .map_err(failed)?;
+    reply.body().u32().map_err(failed)
+}
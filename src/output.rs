@@ -0,0 +1,256 @@
+use std::{process::Command, str::FromStr, time::Duration};
+
+use crate::{errors::Failure, headless};
+
+/// How the bar text is worded.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputStyle {
+    #[default]
+    Standard, // A compact `MM:SS` countdown
+    Accessible, // Spelled-out phase names and durations, friendly to screen readers
+}
+
+impl FromStr for OutputStyle {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "standard" => Ok(OutputStyle::Standard),
+            "accessible" => Ok(OutputStyle::Accessible),
+            _ => Err(format!("Unknown output style {}", name)),
+        }
+    }
+}
+
+/// Placeholders a `--format-text` template may use.
+pub const PLACEHOLDERS: &[&str] = &[
+    "remaining",      // Time left in the phase, `MM:SS`
+    "elapsed",        // Time spent in the phase, `MM:SS`
+    "phase",          // `work`, `short-break` or `long-break`
+    "state",          // The phase, or `paused`, `ready`, `scheduled`, `warmup` or `error`
+    "icon",           // A symbol for the state
+    "completed",      // Pomodoros completed towards the long break
+    "per_long_break", // Pomodoros before each long break
+    "today",          // Work sessions completed today
+    "goal",           // The daily goal, empty without one
+    "streak",         // Days in a row the daily goal was met
+    "task",           // The session's label, empty without one
+    "eta",            // When the phase ends, `HH:MM`, empty while paused
+];
+
+/// Bar text written from a template such as `{icon} {remaining} ({completed}/{per_long_break})`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextTemplate(String);
+
+impl FromStr for TextTemplate {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let Some(length) = rest[start..].find('}') else {
+                return Err(format!("Unclosed placeholder in {}", template));
+            };
+            let name = &rest[start + 1..start + length];
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(format!("Unknown placeholder {{{}}}", name));
+            }
+            rest = &rest[start + length + 1..];
+        }
+        Ok(TextTemplate(template.to_string()))
+    }
+}
+
+impl TextTemplate {
+    /// Fills in the placeholders; `value` gives the text for each name in `PLACEHOLDERS`.
+    pub fn render(&self, value: impl Fn(&str) -> String) -> String {
+        let mut text = String::new();
+        let mut rest = self.0.as_str();
+        while let Some(start) = rest.find('{') {
+            let length = rest[start..].find('}').unwrap_or_default();
+            text.push_str(&rest[..start]);
+            text.push_str(&value(&rest[start + 1..start + length]));
+            rest = &rest[start + length + 1..];
+        }
+        text.push_str(rest);
+        text
+    }
+}
+
+/// States the bar can show, each with its own icon.
+const STATES: &[&str] = &[
+    "work",
+    "short-break",
+    "long-break",
+    "paused",
+    "ready",
+    "overtime",
+    "scheduled",
+    "warmup",
+    "error",
+];
+
+/// A symbol chosen for a state with `--icon <state>=<symbol>`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Icon {
+    state: String,
+    symbol: String,
+}
+
+impl FromStr for Icon {
+    type Err = String;
+
+    fn from_str(icon: &str) -> Result<Self, Self::Err> {
+        let (state, symbol) = icon
+            .split_once('=')
+            .ok_or_else(|| format!("Expected <state>=<symbol>, got {}", icon))?;
+        if !STATES.contains(&state) {
+            return Err(format!("Unknown state {}", state));
+        }
+        Ok(Icon {
+            state: state.to_string(),
+            symbol: symbol.to_string(),
+        })
+    }
+}
+
+/// The symbol shown for each state, the defaults unless `--icon` chose another.
+#[derive(Clone, Debug, Default)]
+pub struct Icons {
+    custom: Vec<Icon>, // The last choice for a state wins
+}
+
+impl Icons {
+    pub fn new(custom: Vec<Icon>) -> Self {
+        Self { custom }
+    }
+
+    pub fn get(&self, state: &str) -> &str {
+        if let Some(icon) = self.custom.iter().rev().find(|icon| icon.state == state) {
+            return &icon.symbol;
+        }
+        match state {
+            "work" => "🍅",
+            "short-break" => "☕",
+            "long-break" => "🛌",
+            "paused" => "⏸",
+            "scheduled" | "warmup" => "⏳",
+            "ready" => "🔔",
+            "overtime" => "⏰",
+            _ => "⚠",
+        }
+    }
+}
+
+/// Which bar the status lines are written for.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Waybar, // JSON with text, tooltip, class and percentage
+    Polybar,  // Text with color and click action tags, for a `tail = true` script
+    I3blocks, // Full text, short text and color lines, for `ctl status`
+    Plain,    // The text alone
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "waybar" => Ok(OutputFormat::Waybar),
+            "polybar" => Ok(OutputFormat::Polybar),
+            "i3blocks" => Ok(OutputFormat::I3blocks),
+            "plain" => Ok(OutputFormat::Plain),
+            _ => Err(format!("Unknown output format {}", name)),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Renders a waybar output object for the bar. `ctl` is the command line that reaches the
+    /// timer, for the click actions.
+    pub fn render(self, output: &serde_json::Value, color: Option<&str>, ctl: &str) -> String {
+        let text = output["text"].as_str().unwrap_or_default();
+        match self {
+            OutputFormat::Waybar => output.to_string(),
+            OutputFormat::Plain => text.to_string(),
+            OutputFormat::I3blocks => format!("{}\n{}\n{}", text, text, color.unwrap_or_default()),
+            OutputFormat::Polybar => {
+                let action = |button: u8, command: &str| {
+                    let command = format!("{} {}", ctl, command).replace(':', "\\:");
+                    format!("%{{A{}:{}:}}", button, command)
+                };
+                let text = match color {
+                    Some(color) => format!("%{{F{}}}{}%{{F-}}", color, text),
+                    None => text.to_string(),
+                };
+                format!(
+                    "{}{}{}%{{A}}%{{A}}",
+                    action(1, "toggle"),
+                    action(3, "skip"),
+                    text
+                )
+            }
+        }
+    }
+
+    /// The bar that `--signal` wakes up.
+    pub fn bar_process(self) -> &'static str {
+        match self {
+            OutputFormat::I3blocks => "i3blocks",
+            _ => "waybar",
+        }
+    }
+}
+
+/// Spells out a duration, rounding up to whole minutes above one minute.
+pub fn spell_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs > 60 {
+        plural(secs.div_ceil(60), "minute")
+    } else {
+        plural(secs, "second")
+    }
+}
+
+/// Spelled-out name of a phase such as `short-break`.
+pub fn spell_phase(phase: &str) -> &'static str {
+    match phase {
+        "short-break" => "Short break",
+        "long-break" => "Long break",
+        _ => "Work",
+    }
+}
+
+/// Escapes text for Pango markup, which waybar reads the text and the tooltip as.
+pub fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+pub fn plural(count: u64, unit: &str) -> String {
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+/// Reads a message aloud through speech-dispatcher.
+pub fn speak(message: &str) -> Result<(), Failure> {
+    if headless::skip("speech", message) {
+        return Ok(());
+    }
+    let status = Command::new("spd-say")
+        .arg(message)
+        .status()
+        .map_err(|err| Failure::new("speech", format!("Failed to run spd-say: {}", err)))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Failure::new("speech", "spd-say exited with an error"))
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+
+use serde_json::json;
+
+use crate::output;
+
+/// A task planned for a number of work sessions.
+#[derive(Clone, Debug)]
+struct Planned {
+    task: String,
+    sessions: u64, // Work sessions still to spend on the task
+}
+
+/// Tasks queued with `queue add`, worked through in order, one label per work session.
+#[derive(Clone, Debug, Default)]
+pub struct Plan {
+    queue: VecDeque<Planned>,
+}
+
+impl Plan {
+    pub fn add(&mut self, task: String, sessions: u64) {
+        self.queue.push_back(Planned { task, sessions });
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    /// The task the next work session is planned for.
+    pub fn current(&self) -> Option<&str> {
+        self.queue.front().map(|planned| planned.task.as_str())
+    }
+
+    /// Counts a work session towards the current task. Returns true when it finished the plan.
+    pub fn complete_session(&mut self) -> bool {
+        let Some(planned) = self.queue.front_mut() else {
+            return false;
+        };
+        planned.sessions = planned.sessions.saturating_sub(1);
+        if planned.sessions == 0 {
+            self.queue.pop_front();
+        }
+        self.queue.is_empty()
+    }
+
+    /// Drops the current task when it is `task`, which was finished early. Returns whether it
+    /// was.
+    pub fn finish(&mut self, task: &str) -> bool {
+        let finished = self.current() == Some(task);
+        if finished {
+            self.queue.pop_front();
+        }
+        finished
+    }
+
+    /// Work sessions left across all queued tasks.
+    pub fn sessions_left(&self) -> u64 {
+        self.queue.iter().map(|planned| planned.sessions).sum()
+    }
+
+    /// The queued tasks in order, one per line, for `queue list`.
+    pub fn summary(&self) -> String {
+        if self.queue.is_empty() {
+            return "The queue is empty".to_string();
+        }
+        self.queue
+            .iter()
+            .enumerate()
+            .map(|(index, planned)| {
+                let sessions = output::plural(planned.sessions, "session");
+                format!("{}. {} ({})", index + 1, planned.task, sessions)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        self.queue
+            .iter()
+            .map(|planned| json!({ "task": planned.task, "sessions": planned.sessions }))
+            .collect()
+    }
+
+    pub fn from_json(value: &serde_json::Value) -> Self {
+        let mut plan = Self::default();
+        for planned in value.as_array().into_iter().flatten() {
+            if let (Some(task), Some(sessions)) =
+                (planned["task"].as_str(), planned["sessions"].as_u64())
+            {
+                plan.add(task.to_string(), sessions);
+            }
+        }
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan() -> Plan {
+        let mut plan = Plan::default();
+        plan.add("write".to_string(), 2);
+        plan.add("review".to_string(), 1);
+        plan
+    }
+
+    #[test]
+    fn tasks_are_worked_through_in_the_order_they_were_added() {
+        let plan = plan();
+        assert_eq!(plan.current(), Some("write"));
+        assert_eq!(plan.sessions_left(), 3);
+        assert_eq!(
+            plan.summary(),
+            "1. write (2 sessions)\n2. review (1 session)"
+        );
+    }
+
+    #[test]
+    fn each_work_session_uses_up_one_session_of_the_head() {
+        let mut plan = plan();
+        assert!(!plan.complete_session());
+        assert_eq!(plan.current(), Some("write"));
+        assert!(!plan.complete_session());
+        assert_eq!(plan.current(), Some("review"));
+        assert!(plan.complete_session());
+        assert_eq!(plan.current(), None);
+        assert!(!plan.complete_session());
+    }
+
+    #[test]
+    fn done_removes_only_the_head() {
+        let mut plan = plan();
+        assert!(!plan.finish("review"));
+        assert!(plan.finish("write"));
+        assert_eq!(plan.current(), Some("review"));
+        assert_eq!(plan.sessions_left(), 1);
+    }
+
+    #[test]
+    fn saved_plans_keep_their_order() {
+        let restored = Plan::from_json(&plan().to_json());
+        assert_eq!(restored.summary(), plan().summary());
+    }
+}
@@ -1,17 +1,41 @@
+use serde::{Serialize, Serializer};
 use serde_json::json;
 
-use crate::dunstify::PomodoroEvent;
+use crate::audio::Player;
+use crate::audit::{AuditLog, Cause};
+use crate::clock::{self, OnSuspend};
+use crate::control;
+use crate::errors::{ErrorLog, Failure};
+use crate::history::{self, History, HistoryLog, Session};
+use crate::latency;
+use crate::mic::MicProbe;
+use crate::milestone::{Milestone, MilestoneAlert};
+use crate::notify;
+use crate::notify::{PhaseChoice, PomodoroEvent, Sounds};
+use crate::output::{self, Icons, OutputFormat, OutputStyle, TextTemplate};
+use crate::plan::Plan;
 use crate::send_notification;
+use crate::status::{Privacy, Status};
+use crate::tasks::TaskSource;
+use crate::theme::Theme;
+use crate::window::{self, FocusedWindow};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
-use std::fs::File;
+use std::fs::{self, DirBuilder, File};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
 
-const STATE_PATH: &str = "pomodoro_state.json";
-
-const POMODORO_DURATION: u64 = 25 * 60; // 25 minutes in seconds
-const SHORT_BREAK_DURATION: u64 = 5 * 60; // 5 minutes in seconds
-const LONG_BREAK_DURATION: u64 = 30 * 60; // 30 minutes in seconds
+const MIN_WORK_DURATION: Duration = Duration::from_secs(60);
+const MAX_WORK_DURATION: Duration = Duration::from_secs(240 * 60);
+const POMODORO_DURATION: Duration = Duration::from_secs(25 * 60); // 25 minutes
+const SHORT_BREAK_DURATION: Duration = Duration::from_secs(5 * 60); // 5 minutes
+const LONG_BREAK_DURATION: Duration = Duration::from_secs(30 * 60); // 30 minutes
 const POMODOROS_PER_LONG_BREAK: u64 = 4; // Number of pomodoros before a long break
+const SNOOZE: Duration = Duration::from_secs(5 * 60); // Delay of *Snooze* on a phase notification
+const COUNTDOWN_INTERVAL: Duration = Duration::from_secs(30); // Between break countdown updates
 /// Enum representing the type of break to take.
 #[derive(PartialEq)]
 enum BreakType {
@@ -19,166 +43,2440 @@ enum BreakType {
     Long,
 }
 
+/// How the breaks handed out so far were used.
+#[derive(Clone, Debug, Default)]
+pub struct BreakCounters {
+    taken: u64,     // Breaks that ran their full length
+    shortened: u64, // Breaks ended early after they had started
+    skipped: u64,   // Breaks ended without any time spent on them
+}
+
+impl BreakCounters {
+    /// Counts a break that ended after `elapsed` out of `length`.
+    fn record(&mut self, elapsed: Duration, length: Duration) {
+        if elapsed >= length {
+            self.taken += 1;
+        } else if !elapsed.is_zero() {
+            self.shortened += 1;
+        } else {
+            self.skipped += 1;
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "taken": self.taken,
+            "shortened": self.shortened,
+            "skipped": self.skipped,
+        })
+    }
+
+    fn from_json(value: &serde_json::Value) -> Self {
+        Self {
+            taken: value["taken"].as_u64().unwrap_or(0),
+            shortened: value["shortened"].as_u64().unwrap_or(0),
+            skipped: value["skipped"].as_u64().unwrap_or(0),
+        }
+    }
+
+    /// Formats the counters with the share of breaks taken in full.
+    fn summary(&self) -> String {
+        let total = self.taken + self.shortened + self.skipped;
+        let compliance = match total {
+            0 => String::new(),
+            _ => format!(" ({}% taken in full)", self.taken * 100 / total),
+        };
+        format!(
+            "Breaks: {} taken, {} shortened, {} skipped{}",
+            self.taken, self.shortened, self.skipped, compliance
+        )
+    }
+}
+
+/// Outcome of a control command, reported back to whoever sent it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reply {
+    Started,
+    AlreadyRunning,
+    Paused,
+    AlreadyPaused,
+    Scheduled,
+    WarmingUp,
+    Cancelled,
+    Skipped,
+    Reset,
+    Adjusted,
+}
+
+impl Reply {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Reply::Started => "started",
+            Reply::AlreadyRunning => "already-running",
+            Reply::Paused => "paused",
+            Reply::AlreadyPaused => "already-paused",
+            Reply::Scheduled => "scheduled",
+            Reply::WarmingUp => "warming-up",
+            Reply::Cancelled => "cancelled",
+            Reply::Skipped => "skipped",
+            Reply::Reset => "reset",
+            Reply::Adjusted => "adjusted",
+        }
+    }
+}
+
+/// The phases the timer cycles through.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Work => "work",
+            Phase::ShortBreak => "short-break",
+            Phase::LongBreak => "long-break",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "work" => Some(Phase::Work),
+            "short-break" => Some(Phase::ShortBreak),
+            "long-break" => Some(Phase::LongBreak),
+            _ => None,
+        }
+    }
+}
+
 /// Struct representing a Pomodoro timer with start, pause, and break functionalities.
 #[derive(Clone, Debug)]
 pub struct Pomodoro {
     start_time: Option<Instant>, // The time at which the Pomodoro was started
     end_time: Option<Instant>,   // The time at which the Pomodoro will end
-    total_time: u64,             // The total time of the Pomodoro in seconds
+    phase: Phase,                // The current phase, kept apart from its length
+    total_time: Duration,        // The total time of the current phase
     pub is_running: bool,        // Flag to indicate if the Pomodoro is currently running
-    elapsed_time: u64,           // The elapsed time of the Pomodoro in seconds
+    elapsed_time: Duration,      // The elapsed time of the current phase
     pomodoros_completed: u64,    // The number of pomodoros completed
-    sound_path: Option< String >,          // The number of pomodoros completed
+    work_duration: Duration,     // Length of a work session
+    short_break_duration: Duration,
+    long_break_duration: Duration,
+    pomodoros_per_long_break: u64, // Work sessions in each cycle, the last followed by a long break
+    namespace: Option<String>,     // Project whose state this timer keeps
+    task: Option<String>,          // What the current work session is about
+    auto_task: bool,               // Label sessions after the focused window when unset
+    focus_interval: Option<Duration>, // How often to sample the focused window during work
+    last_focus_sample: Option<Instant>,
+    focus_time: BTreeMap<String, Duration>, // Time per focused app in the latest work session
+    theme: Theme,                           // Pango styling applied to the bar text
+    output_style: OutputStyle,              // How the bar text is worded
+    format: OutputFormat,                   // Which bar the status lines are written for
+    format_text: Option<TextTemplate>,      // Template for the bar text instead of `MM:SS`
+    icons: Icons,                           // Symbols for the states
+    show_icons: bool,                       // Put the state's icon before the bar text
+    breaks: BreakCounters,                  // Breaks taken, shortened and skipped
+    rollover_cap: Duration,                 // Most unused short-break time added to a long break
+    rollover: Duration, // Unused short-break time waiting for the next long break
+    long_break_bonus: Duration, // Rolled-over time added to the current long break
+    notes: Vec<(String, String)>, // Timestamped notes on the latest work session
+    inbox: PathBuf,     // File that `capture` appends thoughts to
+    captures: u64,      // Thoughts captured during the latest work session
+    task_source: Option<TaskSource>, // Suggests a task when a session starts without one
+    suggested_task: Option<String>, // Suggestion waiting to be offered to the user
+    adjustment: i64,    // Seconds `add`/`sub` put on (or took off) the current phase
+    overtime: bool,     // Count up past the end of a work session until it is skipped
+    overtime_announced: bool, // Whether the current work session has said that it is over
+    on_suspend: OnSuspend, // Whether time spent suspended counts or pauses the timer
+    idle_paused: bool,  // Whether the work session was paused for idleness, to resume
+    session_started: Option<String>, // Local `HH:MM` at which the current work session started
+    history: History,   // Work sessions finished today, for the tooltip
+    plan: Plan,         // Tasks queued for the next work sessions
+    planned: bool,      // Whether the current work session took its task from the plan
+    alerts: Alerts,
+    reminders: Reminders,
+    goal: Goal,
+    reporting: Reporting,
+    schedule: Schedule,
 }
 
-impl Pomodoro {
-    pub fn new(sound_path: Option<String>) -> Self {
+/// How the timer makes itself heard: sounds, notifications and what holds them back.
+#[derive(Clone, Debug)]
+struct Alerts {
+    sounds: Sounds,                        // Sounds played with the notifications
+    normalize_sound: bool,                 // Whether to peak-normalize the sound before playing it
+    player: Player,                        // Program that plays the sound
+    quiet_fullscreen: bool, // Hold back sounds and notifications over fullscreen windows
+    mic_probe: Option<MicProbe>, // Detects calls, during which chimes are muted
+    deferred_event: Option<PomodoroEvent>, // Notification held back until the call ends
+    speak_at: Vec<Duration>, // Remaining times at which to announce the time aloud
+    last_remaining: Option<Duration>, // Remaining time at the previous tick, to detect milestones
+    milestones: Vec<Milestone>, // Points in a work session to announce
+    milestone_alert: MilestoneAlert, // Whether milestones notify, chime or both
+    break_countdown: bool,  // Keep a notification with the time left in a break
+    countdown_checked_at: Option<Instant>, // When the break countdown was last updated or held back
+    countdown_shown: bool,  // Whether the break countdown notification is up
+    phase_actions: bool,    // Offer Start, Snooze and Skip on the notification for the next phase
+    prompt: Option<PomodoroEvent>, // Phase notification waiting to be shown with its actions
+}
+
+/// Nudges back to work, about a paused session or a distracting app.
+#[derive(Clone, Debug)]
+struct Reminders {
+    pause_reminder: Duration, // First reminder about a paused work session; later ones double
+    paused_since: Option<Instant>, // When the current work session was paused
+    next_pause_reminder: Duration, // Paused time at which the next reminder is due
+    distractions: Vec<String>, // App ids or title fragments that count as distractions
+    distraction_limit: Duration, // How long a distraction may keep focus before an alert
+    distracted_since: Option<Instant>, // When focus moved to a distraction
+    distraction_alerted: bool, // Whether the current distraction was already alerted
+}
+
+/// Progress towards the daily goal, and the days in a row that met it.
+#[derive(Clone, Debug)]
+struct Goal {
+    daily_goal: Option<u64>,     // Work sessions to complete each day
+    completed_today: u64,        // Work sessions completed on `today`
+    today: String,               // Local date `completed_today` counts for
+    streak: u64,                 // Consecutive days on which the daily goal was met
+    goal_met_on: Option<String>, // Local date on which the goal was last met
+}
+
+/// What the timer records about itself, and how much of it may leave the process.
+#[derive(Clone, Debug)]
+struct Reporting {
+    privacy: Privacy,              // Whether task labels are left out of reports
+    errors: ErrorLog,              // Recent failures of notifications and sounds
+    audit: AuditLog,               // Append-only log of state transitions
+    history_log: HistoryLog,       // Append-only log of finished phases
+    phase_started: Option<String>, // When the current phase first started, for the history log
+    session_id: Option<String>,    // UUID of the current phase once it has started
+}
+
+/// When phases start other than on `start`: later, after a warm-up, or on their own.
+#[derive(Clone, Debug)]
+struct Schedule {
+    scheduled_start: Option<Instant>, // When a deferred `start --in/--at` fires
+    warmup: Duration,                 // Countdown between `start` and a fresh work session
+    warmup_until: Option<Instant>,    // When the current warm-up ends
+    grace: Duration,                  // "Ready" window after a break in which auto-start holds off
+    ready_until: Option<Instant>,     // When the grace window after the latest break ends
+    auto_start_breaks: bool,          // Start a break right away when the work session runs out
+    auto_start_work: bool,            // Start a work session right away when the break runs out
+}
+
+/// Builder for a [`Pomodoro`], starting from the classic 25/5/30 minute schedule.
+#[derive(Clone, Debug)]
+pub struct PomodoroBuilder {
+    work: Duration,
+    short_break: Duration,
+    long_break: Duration,
+    long_break_every: u64,
+    sounds: Sounds,
+    normalize_sound: bool,
+    player: Player,
+    namespace: Option<String>,
+    auto_task: bool,
+    focus_interval: Option<Duration>,
+    distractions: Vec<String>,
+    distraction_limit: Duration,
+    theme: Theme,
+    output_style: OutputStyle,
+    format: OutputFormat,
+    format_text: Option<TextTemplate>,
+    icons: Icons,
+    show_icons: bool,
+    speak_at: Vec<Duration>,
+    warmup: Duration,
+    grace: Duration,
+    quiet_fullscreen: bool,
+    mic_probe: Option<MicProbe>,
+    rollover_cap: Duration,
+    inbox: Option<PathBuf>,
+    task_source: Option<TaskSource>,
+    pause_reminder: Duration,
+    milestones: Vec<Milestone>,
+    milestone_alert: MilestoneAlert,
+    break_countdown: bool,
+    phase_actions: bool,
+    auto_start_breaks: bool,
+    auto_start_work: bool,
+    overtime: bool,
+    privacy: Privacy,
+    on_suspend: OnSuspend,
+    daily_goal: Option<u64>,
+}
+
+impl Default for PomodoroBuilder {
+    fn default() -> Self {
         Self {
+            work: POMODORO_DURATION,
+            short_break: SHORT_BREAK_DURATION,
+            long_break: LONG_BREAK_DURATION,
+            long_break_every: POMODOROS_PER_LONG_BREAK,
+            sounds: Sounds::default(),
+            normalize_sound: false,
+            player: Player::default(),
+            namespace: None,
+            auto_task: false,
+            focus_interval: None,
+            distractions: Vec::new(),
+            distraction_limit: Duration::ZERO,
+            theme: Theme::default(),
+            output_style: OutputStyle::default(),
+            format: OutputFormat::default(),
+            format_text: None,
+            icons: Icons::default(),
+            show_icons: true,
+            speak_at: Vec::new(),
+            warmup: Duration::ZERO,
+            grace: Duration::ZERO,
+            quiet_fullscreen: false,
+            mic_probe: None,
+            rollover_cap: Duration::ZERO,
+            inbox: None,
+            task_source: None,
+            pause_reminder: Duration::ZERO,
+            milestones: Vec::new(),
+            milestone_alert: MilestoneAlert::default(),
+            break_countdown: false,
+            phase_actions: false,
+            auto_start_breaks: false,
+            auto_start_work: false,
+            overtime: false,
+            privacy: Privacy::default(),
+            on_suspend: OnSuspend::default(),
+            daily_goal: None,
+        }
+    }
+}
+
+impl PomodoroBuilder {
+    pub fn work(mut self, duration: Duration) -> Self {
+        self.work = duration;
+        self
+    }
+
+    pub fn short_break(mut self, duration: Duration) -> Self {
+        self.short_break = duration;
+        self
+    }
+
+    pub fn long_break(mut self, duration: Duration) -> Self {
+        self.long_break = duration;
+        self
+    }
+
+    /// Makes every `count`th work session end in a long break rather than a short one.
+    pub fn long_break_every(mut self, count: u64) -> Self {
+        self.long_break_every = count;
+        self
+    }
+
+    /// Plays each phase's sound with its notification through `player`, optionally peak-normalized
+    /// first.
+    pub fn sound(mut self, sounds: Sounds, normalize: bool, player: Player) -> Self {
+        self.player = player;
+        self.sounds = sounds;
+        self.normalize_sound = normalize;
+        self
+    }
+
+    /// Keeps this timer's state separate from other projects' timers.
+    pub fn namespace(mut self, namespace: Option<String>) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    /// Labels new work sessions after the focused window when no task is set.
+    pub fn auto_task(mut self, auto_task: bool) -> Self {
+        self.auto_task = auto_task;
+        self
+    }
+
+    /// Enables recording which apps are focused during work sessions.
+    pub fn focus_sampling(mut self, interval: Option<Duration>) -> Self {
+        self.focus_interval = interval;
+        self
+    }
+
+    /// Alerts when focus stays on one of `distractions` for longer than `limit` during work.
+    pub fn distractions(mut self, distractions: Vec<String>, limit: Duration) -> Self {
+        self.distractions = distractions;
+        self.distraction_limit = limit;
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Words the bar text, optionally announcing the remaining time aloud at milestones.
+    pub fn output_style(mut self, output_style: OutputStyle, speak_at: Vec<Duration>) -> Self {
+        self.output_style = output_style;
+        self.speak_at = speak_at;
+        self
+    }
+
+    /// Writes the status for `format`, with the bar text from `format_text` when given.
+    pub fn format(mut self, format: OutputFormat, format_text: Option<TextTemplate>) -> Self {
+        self.format = format;
+        self.format_text = format_text;
+        self
+    }
+
+    /// Chooses the state icons, and whether the bar text starts with one.
+    pub fn icons(mut self, icons: Icons, show_icons: bool) -> Self {
+        self.icons = icons;
+        self.show_icons = show_icons;
+        self
+    }
+
+    /// Shows a short countdown before each fresh work session begins.
+    pub fn warmup(mut self, warmup: Duration) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Keeps auto-start from starting work right after a break ends.
+    pub fn grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Holds back sounds and notifications while the focused window is fullscreen.
+    pub fn quiet_fullscreen(mut self, quiet: bool) -> Self {
+        self.quiet_fullscreen = quiet;
+        self
+    }
+
+    /// Mutes chimes and defers notifications while the probe reports the microphone in use.
+    pub fn mic_probe(mut self, probe: Option<MicProbe>) -> Self {
+        self.mic_probe = probe;
+        self
+    }
+
+    /// Rolls unused short-break time into the next long break, up to `cap`.
+    pub fn rollover_cap(mut self, cap: Duration) -> Self {
+        self.rollover_cap = cap;
+        self
+    }
+
+    /// Appends captured thoughts to this file instead of the default inbox.
+    pub fn inbox(mut self, inbox: Option<PathBuf>) -> Self {
+        self.inbox = inbox;
+        self
+    }
+
+    /// Offers the next task from todo.txt or Taskwarrior when a session starts without one.
+    pub fn task_source(mut self, source: Option<TaskSource>) -> Self {
+        self.task_source = source;
+        self
+    }
+
+    /// Reminds about a paused work session after `after`, then at doubling intervals.
+    pub fn pause_reminder(mut self, after: Duration) -> Self {
+        self.pause_reminder = after;
+        self
+    }
+
+    /// Announces the given points of each work session, by notification, sound or both.
+    pub fn milestones(mut self, milestones: Vec<Milestone>, alert: MilestoneAlert) -> Self {
+        self.milestones = milestones;
+        self.milestone_alert = alert;
+        self
+    }
+
+    /// Keeps a notification with the time left during breaks, updated every 30 seconds.
+    pub fn break_countdown(mut self, enabled: bool) -> Self {
+        self.break_countdown = enabled;
+        self
+    }
+
+    /// Adds *Start*, *Snooze 5 min* and *Skip* actions to the notification for the next phase.
+    pub fn phase_actions(mut self, enabled: bool) -> Self {
+        self.phase_actions = enabled;
+        self
+    }
+
+    /// Starts the next break, or the next work session, as soon as the phase before it runs
+    /// out, rather than waiting for `start`.
+    pub fn auto_start_phases(mut self, breaks: bool, work: bool) -> Self {
+        self.auto_start_breaks = breaks;
+        self.auto_start_work = work;
+        self
+    }
+
+    /// Keeps a work session going past its end, counting the overtime, until it is skipped.
+    pub fn overtime(mut self, enabled: bool) -> Self {
+        self.overtime = enabled;
+        self
+    }
+
+    /// Leaves task labels out of everything the timer reports, the bar included, with
+    /// [`Privacy::RedactTasks`]. Only the history log and the state file keep them.
+    pub fn privacy(mut self, privacy: Privacy) -> Self {
+        self.privacy = privacy;
+        self
+    }
+
+    /// Either counts the time the machine spends suspended, so phases end on the wall clock, or
+    /// pauses a running phase over it.
+    pub fn on_suspend(mut self, policy: OnSuspend) -> Self {
+        self.on_suspend = policy;
+        self
+    }
+
+    /// Shows progress towards `goal` work sessions a day, and celebrates reaching it.
+    pub fn daily_goal(mut self, goal: Option<u64>) -> Self {
+        self.daily_goal = goal;
+        self
+    }
+
+    /// Builds the timer, refusing settings that would produce a broken display.
+    pub fn build(self) -> Result<Pomodoro, String> {
+        self.validate()?;
+        let audit = AuditLog::new(self.namespace.as_deref());
+        let history_log = HistoryLog::new(self.namespace.as_deref());
+        Ok(Pomodoro {
             start_time: None,
             end_time: None,
-            total_time: POMODORO_DURATION,
+            phase: Phase::Work,
+            total_time: self.work,
             is_running: false,
-            elapsed_time: 0,
+            elapsed_time: Duration::ZERO,
             pomodoros_completed: 0,
-            sound_path,
+            work_duration: self.work,
+            short_break_duration: self.short_break,
+            long_break_duration: self.long_break,
+            pomodoros_per_long_break: self.long_break_every,
+            namespace: self.namespace,
+            task: None,
+            auto_task: self.auto_task,
+            focus_interval: self.focus_interval,
+            last_focus_sample: None,
+            focus_time: BTreeMap::new(),
+            theme: self.theme,
+            output_style: self.output_style,
+            format: self.format,
+            format_text: self.format_text,
+            icons: self.icons,
+            show_icons: self.show_icons,
+            breaks: BreakCounters::default(),
+            rollover_cap: self.rollover_cap,
+            rollover: Duration::ZERO,
+            long_break_bonus: Duration::ZERO,
+            notes: Vec::new(),
+            inbox: self.inbox.unwrap_or_else(default_inbox),
+            captures: 0,
+            task_source: self.task_source,
+            adjustment: 0,
+            overtime: self.overtime,
+            overtime_announced: false,
+            on_suspend: self.on_suspend,
+            idle_paused: false,
+            session_started: None,
+            history: History::default(),
+            plan: Plan::default(),
+            planned: false,
+            suggested_task: None,
+            alerts: Alerts {
+                sounds: self.sounds,
+                normalize_sound: self.normalize_sound,
+                player: self.player,
+                quiet_fullscreen: self.quiet_fullscreen,
+                mic_probe: self.mic_probe,
+                deferred_event: None,
+                speak_at: self.speak_at,
+                last_remaining: None,
+                milestones: self.milestones,
+                milestone_alert: self.milestone_alert,
+                break_countdown: self.break_countdown,
+                countdown_checked_at: None,
+                countdown_shown: false,
+                phase_actions: self.phase_actions,
+                prompt: None,
+            },
+            reminders: Reminders {
+                pause_reminder: self.pause_reminder,
+                paused_since: None,
+                next_pause_reminder: Duration::ZERO,
+                distractions: self.distractions,
+                distraction_limit: self.distraction_limit,
+                distracted_since: None,
+                distraction_alerted: false,
+            },
+            goal: Goal {
+                daily_goal: self.daily_goal,
+                completed_today: 0,
+                today: clock::local_date(),
+                streak: 0,
+                goal_met_on: None,
+            },
+            reporting: Reporting {
+                privacy: self.privacy,
+                errors: ErrorLog::default(),
+                audit,
+                history_log,
+                phase_started: None,
+                session_id: None,
+            },
+            schedule: Schedule {
+                scheduled_start: None,
+                warmup: self.warmup,
+                warmup_until: None,
+                grace: self.grace,
+                ready_until: None,
+                auto_start_breaks: self.auto_start_breaks,
+                auto_start_work: self.auto_start_work,
+            },
+        })
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !(MIN_WORK_DURATION..=MAX_WORK_DURATION).contains(&self.work) {
+            return Err(format!(
+                "Work duration must be between {} and {} minutes, got {}s",
+                MIN_WORK_DURATION.as_secs() / 60,
+                MAX_WORK_DURATION.as_secs() / 60,
+                self.work.as_secs()
+            ));
+        }
+        if self.long_break_every == 0 {
+            return Err("A cycle needs at least one work session".to_string());
+        }
+        if self.short_break.is_zero() || self.long_break.is_zero() {
+            return Err("Break durations must be longer than zero".to_string());
+        }
+        if self
+            .focus_interval
+            .is_some_and(|interval| interval.is_zero())
+        {
+            return Err("The focus sampling interval must be longer than zero".to_string());
         }
+        Ok(())
+    }
+}
+
+impl Pomodoro {
+    pub fn builder() -> PomodoroBuilder {
+        PomodoroBuilder::default()
+    }
+
+    /// Attributes the time since the last sample to the focused app, if due.
+    pub fn sample_focus(&mut self) {
+        let Some(interval) = self.focus_interval else {
+            return;
+        };
+        if !self.is_running || self.phase != Phase::Work {
+            self.last_focus_sample = None;
+            self.reminders.distracted_since = None;
+            self.reminders.distraction_alerted = false;
+            return;
+        }
+
+        let now = Instant::now();
+        if self
+            .last_focus_sample
+            .is_some_and(|at| now.duration_since(at) < interval)
+        {
+            return;
+        }
+        self.last_focus_sample = Some(now);
+        if let Some(window) = window::focused_window() {
+            self.check_distraction(&window, now);
+            *self.focus_time.entry(window.app_id).or_default() += interval;
+        }
+    }
+
+    fn check_distraction(&mut self, window: &FocusedWindow, now: Instant) {
+        if !self
+            .reminders
+            .distractions
+            .iter()
+            .any(|pattern| window.matches(pattern))
+        {
+            self.reminders.distracted_since = None;
+            self.reminders.distraction_alerted = false;
+            return;
+        }
+
+        let since = *self.reminders.distracted_since.get_or_insert(now);
+        if !self.reminders.distraction_alerted
+            && now.duration_since(since) >= self.reminders.distraction_limit
+        {
+            self.reminders.distraction_alerted = true;
+            if self.alerts.quiet_fullscreen && window.fullscreen {
+                return;
+            }
+            let message = format!("Still on {}? Back to work.", window.app_id);
+            notify::send_message("dialog-information", &message);
+        }
+    }
+
+    /// Summarizes the focus time per app, e.g. `70% code, 20% firefox`.
+    fn focus_breakdown(&self) -> Option<String> {
+        let total: Duration = self.focus_time.values().sum();
+        if total.is_zero() {
+            return None;
+        }
+        let mut apps: Vec<_> = self.focus_time.iter().collect();
+        apps.sort_by(|a, b| b.1.cmp(a.1));
+        let shares: Vec<String> = apps
+            .iter()
+            .take(3)
+            .map(|(app, time)| format!("{}% {}", time.as_secs() * 100 / total.as_secs(), app))
+            .collect();
+        Some(shares.join(", "))
     }
 
-    pub fn start(&mut self) {
-        if !self.is_running {
+    fn state_path(&self) -> PathBuf {
+        match &self.namespace {
+            Some(namespace) => state_file(&format!("state.{}.json", namespace)),
+            None => state_file("state.json"),
+        }
+    }
+
+    pub fn start(&mut self, cause: Cause) -> Reply {
+        let before = self.status();
+        let reply = self.start_phase();
+        self.audit(cause, before);
+        reply
+    }
+
+    pub fn pause(&mut self, cause: Cause) -> Reply {
+        let before = self.status();
+        let reply = self.pause_phase();
+        self.audit(cause, before);
+        reply
+    }
+
+    fn start_phase(&mut self) -> Reply {
+        self.schedule.scheduled_start = None;
+        self.idle_paused = false;
+        if self.is_running {
+            Reply::AlreadyRunning
+        } else {
             let now = Instant::now();
+            self.schedule.ready_until = None;
+            // A `start` during the warm-up skips the rest of it.
+            let fresh_work = self.start_time.is_none() && self.phase == Phase::Work;
+            if fresh_work
+                && !self.schedule.warmup.is_zero()
+                && self.schedule.warmup_until.take().is_none()
+            {
+                self.schedule.warmup_until = Some(now + self.schedule.warmup);
+                return Reply::WarmingUp;
+            }
             if let Some(start_time) = self.start_time {
-                self.end_time = Some(
-                    now + self
-                        .end_time
-                        .unwrap()
-                        .duration_since(start_time),
-                );
+                self.end_time = Some(now + self.end_time.unwrap().duration_since(start_time));
             } else {
-                self.end_time = Some(now + Duration::from_secs(self.total_time));
+                self.end_time = Some(now + self.phase_length());
+                self.reporting.phase_started = Some(clock::local_rfc3339());
+                self.reporting.session_id = Some(history::new_session_id());
+                if self.phase == Phase::Work {
+                    self.session_started = Some(clock::local_time());
+                    self.focus_time.clear();
+                    self.notes.clear();
+                    self.captures = 0;
+                    self.planned = self.task.is_none() && self.plan.current().is_some();
+                    if self.planned {
+                        self.task = self.plan.current().map(str::to_string);
+                    }
+                    if self.auto_task && self.task.is_none() {
+                        self.task = window::focused_window().map(|window| window.label());
+                    }
+                    if self.task.is_none() {
+                        self.suggested_task =
+                            self.task_source.as_ref().and_then(TaskSource::next_task);
+                    }
+                }
             }
             self.start_time = Some(now);
             self.is_running = true;
+            self.reminders.paused_since = None;
+            Reply::Started
         }
     }
 
-    pub fn pause(&mut self) {
+    fn pause_phase(&mut self) -> Reply {
         if self.is_running {
             let now = Instant::now();
-            self.elapsed_time += now.duration_since(self.start_time.unwrap()).as_secs();
+            self.elapsed_time += now.duration_since(self.start_time.unwrap());
             self.is_running = false;
+            if !self.reminders.pause_reminder.is_zero() && self.phase == Phase::Work {
+                self.reminders.paused_since = Some(now);
+                self.reminders.next_pause_reminder = self.reminders.pause_reminder;
+            }
+            Reply::Paused
+        } else if self.schedule.scheduled_start.take().is_some()
+            || self.schedule.warmup_until.take().is_some()
+        {
+            Reply::Cancelled
+        } else {
+            Reply::AlreadyPaused
+        }
+    }
+
+    /// Accounts for `slept`, time the machine spent suspended. `Instant` stands still through a
+    /// suspend, so without this a running phase would lose that time without being paused.
+    pub fn wake_from_suspend(&mut self, slept: Duration) {
+        let before = self.status();
+        eprintln!("Woke up after {}s suspended", slept.as_secs());
+        match self.on_suspend {
+            OnSuspend::Continue => {
+                let deadlines = [
+                    &mut self.start_time,
+                    &mut self.end_time,
+                    &mut self.schedule.scheduled_start,
+                    &mut self.schedule.warmup_until,
+                    &mut self.schedule.ready_until,
+                    &mut self.reminders.paused_since,
+                ];
+                for time in deadlines {
+                    *time = time.map(|time| time.checked_sub(slept).unwrap_or(time));
+                }
+            }
+            OnSuspend::Pause => {
+                self.pause_phase();
+            }
+        }
+        self.audit(Cause::Suspend, before);
+    }
+
+    /// Pauses a running work session when the session goes idle, and resumes it when the session
+    /// is in use again, unless it was started or paused by other means in between. Breaks go on
+    /// either way. Returns the reply if the timer changed.
+    pub fn set_idle(&mut self, idle: bool) -> Option<Reply> {
+        if idle && self.is_running && self.phase == Phase::Work {
+            self.idle_paused = true;
+            Some(self.pause(Cause::Idle))
+        } else if !idle && std::mem::take(&mut self.idle_paused) && !self.is_running {
+            Some(self.start(Cause::Idle))
+        } else {
+            None
+        }
+    }
+
+    /// Arms a deferred start, shown in the bar as a countdown until it fires.
+    pub fn schedule_start(&mut self, delay: Duration, cause: Cause) -> Reply {
+        if self.is_running {
+            return Reply::AlreadyRunning;
+        }
+        let before = self.status();
+        self.schedule.scheduled_start = Some(Instant::now() + delay);
+        self.audit(cause, before);
+        Reply::Scheduled
+    }
+
+    /// Counts the break that is ending and rolls unused short-break time over.
+    fn end_break(&mut self) {
+        let elapsed = self.get_elapsed_time();
+        self.breaks.record(elapsed, self.phase_length());
+        if self.phase == Phase::ShortBreak {
+            let unused = self.phase_length().saturating_sub(elapsed);
+            self.rollover = (self.rollover + unused).min(self.rollover_cap);
+        }
+        self.long_break_bonus = Duration::ZERO;
+    }
+
+    /// Whether a break ended recently enough that work should not start automatically.
+    pub fn in_grace(&self) -> bool {
+        self.schedule
+            .ready_until
+            .is_some_and(|ready_until| Instant::now() < ready_until)
+    }
+
+    /// The phase and whether it is running, as recorded in the audit log.
+    fn status(&self) -> String {
+        let progress = if self.schedule.scheduled_start.is_some() {
+            "scheduled"
+        } else if self.schedule.warmup_until.is_some() {
+            "warming-up"
+        } else if self.is_running {
+            "running"
+        } else {
+            "paused"
+        };
+        format!("{} {}", self.phase_name(), progress)
+    }
+
+    /// Drops a work session left paused from the previous day instead of resuming it,
+    /// logging it as abandoned.
+    fn void_stale_session(&mut self) {
+        if self.phase != Phase::Work || self.is_running || self.start_time.is_none() {
+            return;
+        }
+        let before = self.status();
+        self.log_phase("aborted");
+        self.setup_timer(Phase::Work, self.work_duration);
+        self.task = None;
+        if let Err(failure) =
+            self.reporting
+                .audit
+                .record(Cause::DayRollover, &before, "work abandoned")
+        {
+            self.report(failure);
+        }
+    }
+
+    /// Appends the phase that is ending to the history log, if it was ever started.
+    fn log_phase(&mut self, outcome: &str) {
+        let Some(started) = self.reporting.phase_started.take() else {
+            return;
+        };
+        let mut record = json!({
+            "id": self.reporting.session_id.take(),
+            "started": started,
+            "ended": clock::local_rfc3339(),
+            "phase": self.phase_name(),
+            "outcome": outcome,
+            "elapsed_secs": self.get_elapsed_time().as_secs(),
+        });
+        if self.phase == Phase::Work {
+            record["task"] = json!(self.task);
+            record["notes"] = self.notes_json();
+            record["captures"] = json!(self.captures);
+            if !self.focus_time.is_empty() {
+                let focus: BTreeMap<_, _> = self
+                    .focus_time
+                    .iter()
+                    .map(|(app, time)| (app, time.as_secs()))
+                    .collect();
+                record["focus_secs"] = json!(focus);
+            }
+        }
+        if let Err(failure) = self.reporting.history_log.record(&record) {
+            self.report(failure);
+        }
+    }
+
+    /// Records a transition from `before` to the current status, if the status changed.
+    fn audit(&mut self, cause: Cause, before: String) {
+        let after = self.status();
+        if before == after {
+            return;
+        }
+        if let Err(failure) = self.reporting.audit.record(cause, &before, &after) {
+            self.report(failure);
+        }
+    }
+
+    /// Takes the task suggested for the session that just started, to offer it once.
+    pub fn take_task_suggestion(&mut self) -> Option<String> {
+        self.suggested_task.take()
+    }
+
+    /// Takes the notification for the next phase, to show it with its actions once.
+    pub fn take_prompt(&mut self) -> Option<PomodoroEvent> {
+        self.alerts.prompt.take()
+    }
+
+    /// Applies the action picked on the notification for `event`, unless the timer has
+    /// already moved on from the phase it announced.
+    pub fn answer_prompt(&mut self, event: PomodoroEvent, choice: PhaseChoice) {
+        let phase = match event {
+            PomodoroEvent::Pomodoro => Phase::Work,
+            PomodoroEvent::ShortBreak => Phase::ShortBreak,
+            PomodoroEvent::LongBreak => Phase::LongBreak,
+        };
+        if self.phase != phase || self.start_time.is_some() {
+            return;
+        }
+        match choice {
+            PhaseChoice::Start => self.start(Cause::Notification),
+            PhaseChoice::Snooze => self.schedule_start(SNOOZE, Cause::Notification),
+            PhaseChoice::Skip => self.skip(Cause::Notification),
+        };
+    }
+
+    /// Labels the current session with a suggested task the user accepted.
+    pub fn accept_task(&mut self, task: String) {
+        if self.task.is_none() {
+            self.task = Some(task);
+        }
+    }
+
+    /// Labels the current work session, or the next one while it has yet to start.
+    pub fn set_task(&mut self, task: &str) -> bool {
+        if self.phase != Phase::Work {
+            return false;
+        }
+        self.task = Some(task.to_string());
+        true
+    }
+
+    /// Marks the task complete and clears its label, in any phase; during a break, the task is
+    /// the one of the session that just ended. The task is also closed in the `--suggest-task`
+    /// source, and a planned task leaves the queue.
+    pub fn complete_task(&mut self) -> Result<String, String> {
+        let task = match self.task.take() {
+            Some(task) => task,
+            None if self.phase != Phase::Work => self
+                .history
+                .recent()
+                .last()
+                .and_then(|session| session.task.clone())
+                .ok_or("No task to mark done")?,
+            None => return Err("No task to mark done".to_string()),
+        };
+        if self.plan.finish(&task) {
+            self.planned = false;
+        }
+        let record = json!({ "at": clock::local_rfc3339(), "task": task, "outcome": "done" });
+        if let Err(failure) = self.reporting.history_log.record(&record) {
+            self.report(failure);
+        }
+        let closed = match self
+            .task_source
+            .as_ref()
+            .map(|source| source.complete(&task))
+        {
+            Some(Ok(true)) => " and closed it in the task list",
+            Some(Ok(false)) | None => "",
+            Some(Err(err)) => {
+                self.report(Failure::new("task-source", err));
+                ""
+            }
+        };
+        Ok(format!("done {}{}", task, closed))
+    }
+
+    /// Queues a task for the next `sessions` work sessions that start without a task.
+    pub fn queue_task(&mut self, task: &str, sessions: u64) {
+        self.plan.add(task.to_string(), sessions);
+    }
+
+    pub fn clear_queue(&mut self) {
+        self.plan.clear();
+    }
+
+    /// How long until the queued plan is worked through, breaks included, if the timer keeps
+    /// running from the current phase with `remaining` left in it.
+    fn plan_remaining(&self, remaining: Duration) -> Option<Duration> {
+        let mut sessions = self.plan.sessions_left();
+        if sessions == 0 {
+            return None;
+        }
+        let mut work = self.phase == Phase::Work;
+        // A work session that has yet to start will take its task from the plan.
+        let fresh = self.start_time.is_none() && self.task.is_none();
+        if work && (self.planned || fresh) {
+            sessions -= 1;
+        }
+        let mut total = remaining;
+        let mut cycle = self.pomodoros_completed;
+        while sessions > 0 {
+            if !work {
+                total += self.work_duration;
+                sessions -= 1;
+            } else {
+                cycle += 1;
+                total += match cycle % self.pomodoros_per_long_break {
+                    0 => self.long_break_duration,
+                    _ => self.short_break_duration,
+                };
+            }
+            work = !work;
+        }
+        Some(total)
+    }
+
+    /// The queued tasks, for `queue list`.
+    pub fn queued_tasks(&self) -> String {
+        self.plan.summary()
+    }
+
+    /// Attaches a timestamped note to the current work session, if one has started.
+    pub fn add_note(&mut self, text: &str) -> bool {
+        if self.phase != Phase::Work || self.start_time.is_none() {
+            return false;
+        }
+        self.notes
+            .push((clock::local_timestamp(), text.to_string()));
+        true
+    }
+
+    /// Appends a thought to the inbox as a todo.txt line and confirms it with a notification.
+    pub fn capture(&mut self, text: &str) -> Result<(), Failure> {
+        let line = format!("{} {}\n", clock::local_date(), text);
+        self.inbox
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| {
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.inbox)
+            })
+            .and_then(|mut inbox| inbox.write_all(line.as_bytes()))
+            .map_err(|err| Failure::new("inbox", format!("{}: {}", self.inbox.display(), err)))?;
+        self.captures += 1;
+        notify::send_message("document-save", &format!("Captured: {}", text));
+        Ok(())
+    }
+
+    /// Adds the phase, today's count and the way to the next long break to the tooltip.
+    fn append_statistics(
+        &self,
+        output: &mut serde_json::Value,
+        status: &Status,
+        remaining: Duration,
+    ) {
+        let phase = status.phase;
+        append_tooltip(output, format!("Phase: {}", output::spell_phase(phase)));
+        let today = match status.goal {
+            Some(goal) => format!("{}/{} pomodoros", status.completed_today, goal),
+            None => output::plural(status.completed_today, "pomodoro"),
+        };
+        append_tooltip(output, format!("Today: {}", today));
+        if let Some(streak) = status.streak.filter(|streak| *streak > 0) {
+            append_tooltip(output, format!("Streak: {}", output::plural(streak, "day")));
+        }
+        if phase == "long-break" {
+            return;
+        }
+        // Work sessions still ahead of the long break, besides the current one.
+        let sessions_left = status.cycle_length - status.cycle - 1;
+        let mut until_long_break =
+            remaining + (self.short_break_duration + self.work_duration) * sessions_left as u32;
+        if phase == "short-break" {
+            until_long_break += self.work_duration;
+        }
+        append_tooltip(
+            output,
+            format!("Long break in {}", output::spell_duration(until_long_break)),
+        );
+    }
+
+    fn notes_json(&self) -> serde_json::Value {
+        self.notes
+            .iter()
+            .map(|(at, text)| json!({ "at": at, "text": text }))
+            .collect()
+    }
+
+    /// Summarizes how breaks were used and how long notifications and sounds took, for the
+    /// `stats` command.
+    pub fn stats(&self) -> String {
+        let latency = latency::summary();
+        if latency.is_empty() {
+            return self.breaks.summary();
+        }
+        format!("{}\n{}", self.breaks.summary(), latency)
+    }
+
+    /// The last `count` recorded transitions, oldest first.
+    pub fn recent_transitions(&self, count: usize) -> String {
+        self.reporting.audit.last(count)
+    }
+
+    /// Formats a reply together with the current phase and remaining time.
+    pub fn describe(&self, reply: Reply) -> String {
+        let status = self.snapshot();
+        format!(
+            "{} phase={} remaining={}",
+            reply.as_str(),
+            status.phase,
+            format_minutes(status.remaining)
+        )
+    }
+
+    /// The state shared by replies, the dump, metrics and the tooltip.
+    pub fn snapshot(&self) -> Status {
+        let elapsed = self.get_elapsed_time();
+        Status {
+            phase: self.phase_name(),
+            remaining: self.phase_length().saturating_sub(elapsed),
+            elapsed,
+            running: self.is_running,
+            cycle: self.cycle(),
+            cycle_length: self.pomodoros_per_long_break,
+            completed_today: self.goal.completed_today,
+            goal: self.goal.daily_goal,
+            streak: self.goal.daily_goal.map(|_| self.current_streak()),
+            task: self.reported_task().map(str::to_string),
+            session: self.reporting.session_id.clone(),
+        }
+    }
+
+    /// Today's work sessions from the history log with their notes, for the `report` command.
+    pub fn daily_report(&self) -> Result<String, Failure> {
+        let report = self.reporting.history_log.report(
+            &clock::local_date(),
+            self.reporting.privacy == Privacy::RedactTasks,
+        )?;
+        if report.is_empty() {
+            return Ok("No work sessions logged today".to_string());
+        }
+        Ok(report)
+    }
+
+    pub fn privacy(&self) -> Privacy {
+        self.reporting.privacy
+    }
+
+    /// The task as it may appear in output that leaves the process, none with
+    /// [`Privacy::RedactTasks`].
+    fn reported_task(&self) -> Option<&str> {
+        self.task
+            .as_deref()
+            .filter(|_| self.reporting.privacy != Privacy::RedactTasks)
+    }
+
+    /// Counts today towards the streak, starting over after a day that missed the goal.
+    fn extend_streak(&mut self) {
+        if self.goal.goal_met_on.as_deref() == Some(self.goal.today.as_str()) {
+            return;
+        }
+        if self.goal.goal_met_on != Some(clock::local_yesterday()) {
+            self.goal.streak = 0;
+        }
+        self.goal.streak += 1;
+        self.goal.goal_met_on = Some(self.goal.today.clone());
+    }
+
+    /// Days in a row the goal was met, up to today. A streak that last counted yesterday still
+    /// stands, since today's goal can still be met.
+    fn current_streak(&self) -> u64 {
+        let met = self.goal.goal_met_on.as_deref();
+        if met == Some(self.goal.today.as_str()) || met == Some(clock::local_yesterday().as_str()) {
+            self.goal.streak
+        } else {
+            0
+        }
+    }
+
+    fn goal_reached(&self) -> bool {
+        self.goal
+            .daily_goal
+            .is_some_and(|goal| self.goal.completed_today >= goal)
+    }
+
+    /// The state the bar text is styled for, also emitted as the waybar `class`.
+    fn display_state(&self) -> &'static str {
+        if self.reporting.errors.active().is_some() {
+            "error"
+        } else if self.schedule.scheduled_start.is_some() {
+            "scheduled"
+        } else if self.schedule.warmup_until.is_some() {
+            "warmup"
+        } else if self.in_grace() {
+            "ready"
+        } else if !self.is_running {
+            "paused"
+        } else if !self.overtime_spent().is_zero() {
+            "overtime"
+        } else {
+            self.phase_name()
         }
     }
 
-    /// Starts a break with the given duration.
-    pub fn setup_timer(&mut self, break_duration: u64) {
-        self.total_time = break_duration;
-        self.elapsed_time = 0;
+    /// How far a work session has run past its end with `--overtime`.
+    fn overtime_spent(&self) -> Duration {
+        if !self.overtime || self.phase != Phase::Work {
+            return Duration::ZERO;
+        }
+        self.get_elapsed_time().saturating_sub(self.phase_length())
+    }
+
+    fn phase_name(&self) -> &'static str {
+        self.phase.as_str()
+    }
+
+    /// Sets up `phase` to last `duration`, waiting for a start.
+    pub fn setup_timer(&mut self, phase: Phase, duration: Duration) {
+        self.phase = phase;
+        self.total_time = duration;
+        self.elapsed_time = Duration::ZERO;
         self.is_running = false;
         self.start_time = None;
         self.end_time = None;
+        self.reminders.paused_since = None;
+        self.adjustment = 0;
+        self.overtime_announced = false;
+        self.idle_paused = false;
+    }
+
+    /// Starts the timer with the phase lasting `length`, if it has not started yet. A phase
+    /// already under way keeps its length.
+    pub fn start_for(&mut self, length: Duration, cause: Cause) -> Reply {
+        if self.start_time.is_none() {
+            self.adjustment = length.as_secs() as i64 - self.total_time.as_secs() as i64;
+        }
+        self.start(cause)
     }
 
-    fn get_elapsed_time(self) -> u64 {
+    /// Lengthens the current phase by `seconds`, or shortens it when negative. Returns `None`
+    /// and leaves the phase alone when it would end too far in the future to keep track of.
+    pub fn adjust(&mut self, seconds: i64) -> Option<Reply> {
+        let adjustment = self.adjustment.checked_add(seconds)?;
+        let previous = std::mem::replace(&mut self.adjustment, adjustment);
+        let remaining = self.phase_length().saturating_sub(self.get_elapsed_time());
+        let Some(end_time) = Instant::now().checked_add(remaining) else {
+            self.adjustment = previous;
+            return None;
+        };
         if self.is_running {
-            self.elapsed_time
-                + Instant::now()
-                    .duration_since(self.start_time.unwrap())
-                    .as_secs()
+            self.end_time = Some(end_time);
+        }
+        Some(Reply::Adjusted)
+    }
+
+    /// The length of the current phase, including any `add`/`sub` adjustment.
+    fn phase_length(&self) -> Duration {
+        let seconds = (self.total_time.as_secs() as i64).saturating_add(self.adjustment);
+        Duration::from_secs(seconds.max(0) as u64)
+    }
+
+    /// Discards the progress of the current phase without advancing to the next one.
+    pub fn cancel_phase(&mut self, cause: Cause) {
+        let before = self.status();
+        self.schedule.warmup_until = None;
+        self.setup_timer(self.phase, self.total_time);
+        self.audit(cause, before);
+    }
+
+    fn get_elapsed_time(&self) -> Duration {
+        if self.is_running {
+            self.elapsed_time + Instant::now().duration_since(self.start_time.unwrap())
         } else {
             self.elapsed_time
         }
     }
 
-    fn get_total_time_and_break_type(self) -> (u64, BreakType) {
-        match self.pomodoros_completed {
-            POMODOROS_PER_LONG_BREAK => (LONG_BREAK_DURATION, BreakType::Long),
-            _ => (SHORT_BREAK_DURATION, BreakType::Short),
+    /// How long the timer thread may sleep before the output or a pending alert can change.
+    /// A running phase and the countdowns wake on the next displayed second; an idle timer only
+    /// needs to look again for the paused minutes, the pause reminder and the wall-clock minute
+    /// that schedules and the day rollover go by.
+    pub fn next_wake(&self) -> Duration {
+        let ticking = self.is_running
+            || self.schedule.scheduled_start.is_some()
+            || self.schedule.warmup_until.is_some()
+            || self.in_grace()
+            || self.reporting.errors.active().is_some()
+            || self.alerts.deferred_event.is_some();
+        if ticking {
+            let into_second = self.get_elapsed_time().subsec_nanos();
+            return Duration::from_secs(1) - Duration::from_nanos(into_second.into());
+        }
+        let minute = Duration::from_secs(60);
+        let into_minute = Duration::from_secs(clock::local_now().tm_sec as u64);
+        let mut wake = minute.saturating_sub(into_minute);
+        if let Some(paused_since) = self.reminders.paused_since {
+            let paused = paused_since.elapsed();
+            let into_paused_minute =
+                Duration::from_nanos((paused.as_nanos() % minute.as_nanos()) as u64);
+            wake = wake.min(minute - into_paused_minute);
+            if !self.reminders.pause_reminder.is_zero() {
+                wake = wake.min(self.reminders.next_pause_reminder.saturating_sub(paused));
+            }
         }
+        wake.max(Duration::from_millis(10))
     }
 
-    fn handle_elapsed_time_over_total_time(
-        &mut self,
-        total_time: u64,
-        break_type: BreakType,
-    ) -> String {
-        if total_time == LONG_BREAK_DURATION || total_time == SHORT_BREAK_DURATION {
-            if self.is_running {
-                send_notification(PomodoroEvent::Pomodoro, self.sound_path.as_deref());
-                self.setup_timer(POMODORO_DURATION)
+    /// The pomodoros completed towards the next long break.
+    fn cycle(&self) -> u64 {
+        self.pomodoros_completed % self.pomodoros_per_long_break
+    }
+
+    /// The break that follows the current work session. Counting with the remainder keeps the
+    /// cycle right when a saved count comes from a run with a different `--long-break-every`.
+    fn next_break_type(&self) -> BreakType {
+        match (self.pomodoros_completed + 1) % self.pomodoros_per_long_break {
+            0 => BreakType::Long,
+            _ => BreakType::Short,
+        }
+    }
+
+    /// Sends a notification for the event, recording any failure.
+    fn notify(&mut self, event: PomodoroEvent) {
+        if self.alerts_muted() {
+            eprintln!("Not notifying while a fullscreen window has focus");
+            return;
+        }
+        if self.in_call() {
+            eprintln!("Deferring the notification until the call ends");
+            self.alerts.deferred_event = Some(event);
+            return;
+        }
+        let sent = if self.alerts.phase_actions {
+            // Shown from the main loop, which can wait for an action to be picked.
+            self.alerts.prompt = Some(event);
+            self.alerts.sounds.for_event(event).map_or(Ok(()), |sound| {
+                notify::play_chime(
+                    self.alerts.player,
+                    sound,
+                    self.alerts.normalize_sound,
+                    self.namespace.as_deref(),
+                )
+            })
+        } else {
+            send_notification(
+                event,
+                &self.alerts.sounds,
+                self.alerts.normalize_sound,
+                self.namespace.as_deref(),
+                self.alerts.player,
+            )
+        };
+        if let Err(failure) = sent {
+            self.report(failure);
+        }
+    }
+
+    /// Whether the microphone is in use, e.g. by a call.
+    fn in_call(&self) -> bool {
+        self.alerts.mic_probe.is_some_and(MicProbe::in_use)
+    }
+
+    /// Reminds that a work session is still paused, waiting twice as long before each
+    /// further reminder.
+    fn remind_paused(&mut self) {
+        let Some(paused_since) = self.reminders.paused_since else {
+            return;
+        };
+        let paused = paused_since.elapsed();
+        if paused < self.reminders.next_pause_reminder || self.alerts_muted() || self.in_call() {
+            return;
+        }
+        self.reminders.next_pause_reminder *= 2;
+        let message = format!("Still paused, {} min", paused.as_secs() / 60);
+        notify::send_message("tomato", &message);
+    }
+
+    /// Sends a notification deferred during a call once the call is over, without the chime.
+    fn send_deferred(&mut self) {
+        let Some(event) = self.alerts.deferred_event else {
+            return;
+        };
+        if self.in_call() {
+            return;
+        }
+        self.alerts.deferred_event = None;
+        notify::send_message(event.icon(), event.message());
+    }
+
+    /// Whether sounds and notifications are held back for a fullscreen window.
+    fn alerts_muted(&self) -> bool {
+        self.alerts.quiet_fullscreen
+            && window::focused_window().is_some_and(|window| window.fullscreen)
+    }
+
+    /// Records a failure so it is surfaced in the bar and by the `errors` command.
+    pub fn report(&mut self, failure: Failure) {
+        self.reporting.errors.record(failure);
+    }
+
+    /// Lists the recent failures, oldest first.
+    pub fn recent_errors(&self) -> String {
+        self.reporting.errors.summary()
+    }
+
+    fn handle_elapsed_time_over_total_time(&mut self, total_time: Duration) -> serde_json::Value {
+        let before = self.status();
+        if self.is_running || self.phase == Phase::Work {
+            self.advance();
+            let auto_start = match self.phase {
+                Phase::Work => self.schedule.auto_start_work,
+                Phase::ShortBreak | Phase::LongBreak => self.schedule.auto_start_breaks,
+            };
+            if auto_start {
+                self.start_phase();
+            }
+        }
+        self.audit(Cause::AutoAdvance, before);
+        json!({
+            "elapsed_time": format_minutes(Duration::ZERO),
+            "text": format_minutes(total_time)
+        })
+    }
+
+    /// Goes back to a fresh work session at the start of the cycle.
+    pub fn reset(&mut self, cause: Cause) -> Reply {
+        let before = self.status();
+        self.schedule.scheduled_start = None;
+        self.schedule.warmup_until = None;
+        self.schedule.ready_until = None;
+        self.pomodoros_completed = 0;
+        self.rollover = Duration::ZERO;
+        self.long_break_bonus = Duration::ZERO;
+        self.log_phase("aborted");
+        self.task = None;
+        self.setup_timer(Phase::Work, self.work_duration);
+        self.audit(cause, before);
+        Reply::Reset
+    }
+
+    /// Ends the current phase right away and sets up the next one, as if it had run out.
+    pub fn skip(&mut self, cause: Cause) -> Reply {
+        let before = self.status();
+        self.schedule.scheduled_start = None;
+        self.schedule.warmup_until = None;
+        self.schedule.ready_until = None;
+        self.advance();
+        self.audit(cause, before);
+        Reply::Skipped
+    }
+
+    /// Ends the current phase with its notification and sets up the next one.
+    fn advance(&mut self) {
+        let completed = self.get_elapsed_time() >= self.phase_length();
+        self.log_phase(if completed { "completed" } else { "aborted" });
+        if self.phase != Phase::Work {
+            self.end_break();
+            self.notify(PomodoroEvent::Pomodoro);
+            self.setup_timer(Phase::Work, self.work_duration);
+            if !self.schedule.grace.is_zero() {
+                self.schedule.ready_until = Some(Instant::now() + self.schedule.grace);
             }
         } else {
+            let ended = clock::local_time();
+            self.history.record(Session {
+                started: self.session_started.take().unwrap_or_else(|| ended.clone()),
+                ended,
+                task: self.task.take(),
+            });
+            self.goal.completed_today += 1;
+            if self.goal.daily_goal == Some(self.goal.completed_today) {
+                self.extend_streak();
+                let message = format!(
+                    "Daily goal reached: {}! Streak: {}",
+                    output::plural(self.goal.completed_today, "pomodoro"),
+                    output::plural(self.goal.streak, "day")
+                );
+                notify::send_message("tomato", &message);
+            }
+            if std::mem::take(&mut self.planned) && self.plan.complete_session() {
+                notify::send_message("tomato", "The plan is complete.");
+            }
+            let break_type = self.next_break_type();
+            self.pomodoros_completed += 1;
             match break_type {
                 BreakType::Long => {
-                    send_notification(PomodoroEvent::LongBreak, self.sound_path.as_deref());
-                    self.pomodoros_completed = 0;
-                    self.setup_timer(LONG_BREAK_DURATION);
+                    self.notify(PomodoroEvent::LongBreak);
+                    self.long_break_bonus = std::mem::take(&mut self.rollover);
+                    self.setup_timer(
+                        Phase::LongBreak,
+                        self.long_break_duration + self.long_break_bonus,
+                    );
                 }
                 BreakType::Short => {
-                    self.pomodoros_completed += 1;
-                    send_notification(PomodoroEvent::ShortBreak, self.sound_path.as_deref());
-                    self.setup_timer(SHORT_BREAK_DURATION);
+                    self.notify(PomodoroEvent::ShortBreak);
+                    self.setup_timer(Phase::ShortBreak, self.short_break_duration);
                 }
             }
         }
-        let elapsed_time_str = format!("{:02}:{:02}", 0, 0);
-        let remaining_time_str = format!("{:02}:{:02}", total_time / 60, total_time % 60);
+    }
+
+    fn handle_remaining_time(total_time: Duration, elapsed_time: Duration) -> serde_json::Value {
+        let remaining_time = total_time - elapsed_time;
+        let elapsed_time_str = format_minutes(elapsed_time);
+        let remaining_time_str = format_minutes(remaining_time);
+
         json!({
             "elapsed_time": elapsed_time_str,
             "text": remaining_time_str
         })
-        .to_string()
     }
 
-    fn handle_remaining_time(total_time: u64, elapsed_time: u64) -> String {
-        let remaining_time = total_time - elapsed_time;
-        let elapsed_time_str = format!("{:02}:{:02}", elapsed_time / 60, elapsed_time % 60);
-        let remaining_time_str = format!("{:02}:{:02}", remaining_time / 60, remaining_time % 60);
+    fn handle_scheduled_start(wait: Duration) -> serde_json::Value {
+        let wait_str = format_minutes(wait);
+        json!({
+            "elapsed_time": "00:00",
+            "text": wait_str,
+            "tooltip": format!("Starts in {}", wait_str)
+        })
+    }
+
+    /// Counts up past the end of a work session, saying once that it is over.
+    fn handle_overtime(&mut self, overtime: Duration) -> serde_json::Value {
+        if !std::mem::replace(&mut self.overtime_announced, true)
+            && !self.alerts_muted()
+            && !self.in_call()
+        {
+            let sound = self.alerts.sounds.for_event(match self.next_break_type() {
+                BreakType::Long => PomodoroEvent::LongBreak,
+                BreakType::Short => PomodoroEvent::ShortBreak,
+            });
+            notify::send_message("tomato", "Time is up. Skip when you are ready.");
+            let sent = sound.map_or(Ok(()), |sound| {
+                notify::play_chime(
+                    self.alerts.player,
+                    sound,
+                    self.alerts.normalize_sound,
+                    self.namespace.as_deref(),
+                )
+            });
+            if let Err(failure) = sent {
+                self.report(failure);
+            }
+        }
+        json!({
+            "elapsed_time": format_minutes(self.phase_length() + overtime),
+            "text": format!("+{}", format_minutes(overtime)),
+            "tooltip": "Work session over, skip to go on to the break"
+        })
+    }
 
+    fn handle_ready() -> serde_json::Value {
         json!({
-            "elapsed_time": elapsed_time_str,
-            "text": remaining_time_str
+            "elapsed_time": "00:00",
+            "text": "ready",
+            "tooltip": "Break over, start when you are ready"
+        })
+    }
+
+    fn handle_warmup(wait: Duration) -> serde_json::Value {
+        json!({
+            "elapsed_time": "00:00",
+            "text": format!("starting in {}…", wait.as_secs()),
+            "tooltip": "Work session about to start"
+        })
+    }
+
+    /// Words the state for screen readers, e.g. `Work paused, 12 minutes remaining`.
+    fn accessible_text(&self, remaining: Duration) -> String {
+        let phase = output::spell_phase(self.phase_name());
+        let remaining = output::spell_duration(remaining);
+        if self.schedule.scheduled_start.is_some() || self.schedule.warmup_until.is_some() {
+            format!("{} starts in {}", phase, remaining)
+        } else if self.in_grace() {
+            "Break over, ready for work".to_string()
+        } else if !self.overtime_spent().is_zero() {
+            let overtime = output::spell_duration(self.overtime_spent());
+            format!("{}, {} over", phase, overtime)
+        } else if self.is_running {
+            format!("{}, {} remaining", phase, remaining)
+        } else {
+            format!("{} paused, {} remaining", phase, remaining)
+        }
+    }
+
+    fn template_text(
+        &self,
+        template: &TextTemplate,
+        status: &Status,
+        output: &serde_json::Value,
+        remaining: Duration,
+    ) -> String {
+        template.render(|name| {
+            let value = self.placeholder(name, status, output, remaining);
+            // Waybar reads the text as Pango markup, which task labels must not break.
+            if self.format == OutputFormat::Waybar {
+                output::escape_markup(&value)
+            } else {
+                value
+            }
         })
-        .to_string()
+    }
+
+    /// The value of one of the template's placeholders.
+    fn placeholder(
+        &self,
+        name: &str,
+        status: &Status,
+        output: &serde_json::Value,
+        remaining: Duration,
+    ) -> String {
+        match name {
+            "remaining" => format_minutes(remaining),
+            "elapsed" => output["elapsed_time"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            "phase" => status.phase.to_string(),
+            "state" => self.display_state().to_string(),
+            "icon" => self.icons.get(self.display_state()).to_string(),
+            "completed" => status.cycle.to_string(),
+            "per_long_break" => status.cycle_length.to_string(),
+            "today" => status.completed_today.to_string(),
+            "streak" => status.streak.unwrap_or_default().to_string(),
+            "goal" => status
+                .goal
+                .map_or_else(String::new, |goal| goal.to_string()),
+            "task" => status.task.clone().unwrap_or_default(),
+            "eta" if self.is_running => clock::local_time_after(remaining),
+            _ => String::new(),
+        }
+    }
+
+    /// Speaks the remaining time when a running phase crosses one of `speak_at`, and
+    /// announces the work session milestones it crosses.
+    fn announce_milestones(&mut self, remaining: Duration) {
+        let previous = self.alerts.last_remaining.replace(remaining);
+        if !self.is_running
+            || self.schedule.scheduled_start.is_some()
+            || self.schedule.warmup_until.is_some()
+        {
+            return;
+        }
+        let Some(previous) = previous else {
+            return;
+        };
+        let crossed = self
+            .alerts
+            .speak_at
+            .iter()
+            .any(|milestone| previous > *milestone && remaining <= *milestone);
+        if crossed && !self.alerts_muted() && !self.in_call() {
+            let message = format!(
+                "{}, {} remaining",
+                output::spell_phase(self.phase_name()),
+                output::spell_duration(remaining)
+            );
+            if let Err(failure) = output::speak(&message) {
+                self.report(failure);
+            }
+        }
+
+        if self.phase != Phase::Work {
+            return;
+        }
+        let length = self.phase_length();
+        let reached = self.alerts.milestones.iter().copied().find(|milestone| {
+            let at = milestone.remaining_at(length);
+            previous > at && remaining <= at
+        });
+        // Probing windows and the microphone is slow, so only when there is something to say.
+        if let Some(milestone) = reached.filter(|_| !self.alerts_muted() && !self.in_call()) {
+            self.alert_milestone(milestone);
+        }
+    }
+
+    /// Updates the break countdown notification, and closes it once the break is over.
+    fn update_break_countdown(&mut self, remaining: Duration) {
+        let on_break = self.is_running && self.phase != Phase::Work;
+        if !self.alerts.break_countdown || !on_break {
+            self.alerts.countdown_checked_at = None;
+            self.close_countdown();
+            return;
+        }
+        if self
+            .alerts
+            .countdown_checked_at
+            .is_some_and(|checked| checked.elapsed() < COUNTDOWN_INTERVAL)
+        {
+            return;
+        }
+        // Probing windows is slow, so only once an update is due.
+        self.alerts.countdown_checked_at = Some(Instant::now());
+        if self.alerts_muted() {
+            self.close_countdown();
+            return;
+        }
+        self.alerts.countdown_shown = true;
+        let message = format!(
+            "{}: {} left, then a work session",
+            output::spell_phase(self.phase_name()),
+            format_minutes(remaining)
+        );
+        notify::show_countdown(&message);
+    }
+
+    fn close_countdown(&mut self) {
+        if std::mem::take(&mut self.alerts.countdown_shown) {
+            notify::close_countdown();
+        }
+    }
+
+    fn alert_milestone(&mut self, milestone: Milestone) {
+        if self.alerts.milestone_alert.notifies() {
+            notify::send_message("tomato", milestone.message());
+        }
+        // Milestones happen during work, so they chime like the start of a session.
+        if let Some(sound) = self
+            .alerts
+            .sounds
+            .for_event(PomodoroEvent::Pomodoro)
+            .map(str::to_string)
+            .filter(|_| self.alerts.milestone_alert.plays_sound())
+        {
+            if let Err(failure) = notify::play_chime(
+                self.alerts.player,
+                &sound,
+                self.alerts.normalize_sound,
+                self.namespace.as_deref(),
+            ) {
+                self.report(failure);
+            }
+        }
     }
 
     pub fn current_pomodoro(&mut self) -> String {
-        let elapsed_time = self.clone().get_elapsed_time();
-        let (total_time, break_type) = self.clone().get_total_time_and_break_type();
+        self.send_deferred();
+        self.remind_paused();
+        let today = clock::local_date();
+        if today != self.goal.today {
+            self.goal.today = today;
+            self.goal.completed_today = 0;
+            self.history.clear();
+            self.void_stale_session();
+        }
+        if self
+            .schedule
+            .scheduled_start
+            .is_some_and(|start_at| Instant::now() >= start_at)
+        {
+            self.start(Cause::Schedule);
+        }
+        if self
+            .schedule
+            .warmup_until
+            .is_some_and(|start_at| Instant::now() >= start_at)
+        {
+            self.start(Cause::Warmup);
+        }
+
+        // Whole seconds, so the elapsed and remaining times shown always add up.
+        let elapsed_time = Duration::from_secs(self.get_elapsed_time().as_secs());
+        let total_time = self.phase_length();
 
-        if elapsed_time > total_time {
-            self.handle_elapsed_time_over_total_time(total_time, break_type)
+        let (mut output, remaining) = if let Some(start_at) = self.schedule.scheduled_start {
+            // Round up so the countdown reaches 00:00 exactly when the session starts.
+            let wait = Duration::from_secs(start_at.duration_since(Instant::now()).as_secs() + 1);
+            (Pomodoro::handle_scheduled_start(wait), wait)
+        } else if let Some(start_at) = self.schedule.warmup_until {
+            let wait = Duration::from_secs(start_at.duration_since(Instant::now()).as_secs() + 1);
+            (Pomodoro::handle_warmup(wait), wait)
+        } else if self.in_grace() {
+            (Pomodoro::handle_ready(), total_time)
+        } else if elapsed_time > total_time && self.overtime && self.phase == Phase::Work {
+            (
+                self.handle_overtime(elapsed_time - total_time),
+                Duration::ZERO,
+            )
+        } else if elapsed_time > total_time {
+            let output = self.handle_elapsed_time_over_total_time(total_time);
+            (output, total_time)
         } else {
-            Pomodoro::handle_remaining_time(total_time, elapsed_time)
+            let output = Pomodoro::handle_remaining_time(total_time, elapsed_time);
+            (output, total_time - elapsed_time)
+        };
+        self.announce_milestones(remaining);
+        self.update_break_countdown(remaining);
+        // The bar reports the same state as the replies, the dump and the metrics.
+        let status = self.snapshot();
+        if let Some(template) = &self.format_text {
+            output["text"] = json!(self.template_text(template, &status, &output, remaining));
+        } else if self.output_style == OutputStyle::Accessible {
+            output["text"] = json!(self.accessible_text(remaining));
+        }
+        let paused_minutes = self
+            .reminders
+            .paused_since
+            .map_or(0, |since| since.elapsed().as_secs() / 60);
+        // A template says itself whether to show the pause, through `{state}` or `{icon}`.
+        if let Some(text) = output["text"]
+            .as_str()
+            .filter(|_| paused_minutes > 0 && self.format_text.is_none())
+        {
+            output["text"] = json!(format!("{} (paused {} min)", text, paused_minutes));
+        }
+        // A template places the icon itself, and spelled-out text already names the phase.
+        let standard = self.format_text.is_none() && self.output_style == OutputStyle::Standard;
+        if let Some(text) = output["text"]
+            .as_str()
+            .filter(|_| self.show_icons && standard)
+        {
+            output["text"] = json!(format!("{} {}", self.icons.get(self.display_state()), text));
+        }
+        // Waybar takes a list of classes, so the goal can be styled on top of the state.
+        output["class"] = if self.goal_reached() {
+            json!([self.display_state(), "goal-reached"])
+        } else {
+            json!(self.display_state())
+        };
+        output["percentage"] = json!(percentage(elapsed_time, total_time));
+        if let Some(failure) = self.reporting.errors.active() {
+            output["tooltip"] = json!(format!("error: {}", failure.code));
+        }
+        self.append_statistics(&mut output, &status, remaining);
+        // End times only hold while the clock runs, so a paused timer shows none, and overtime
+        // lasts until it is skipped.
+        if self.is_running && self.overtime_spent().is_zero() {
+            let eta = clock::local_time_after(remaining);
+            append_tooltip(&mut output, format!("Ends at {}", eta));
+            output["eta"] = json!(eta);
+            if let Some(left) = self.plan_remaining(remaining) {
+                let plan_eta = clock::local_time_after(left);
+                append_tooltip(&mut output, format!("Plan ends at {}", plan_eta));
+                output["plan_eta"] = json!(plan_eta);
+            }
+        }
+        // Only waybar renders Pango markup; the other bars get the theme's color alone.
+        if let Some(text) = output["text"]
+            .as_str()
+            .filter(|_| self.format == OutputFormat::Waybar)
+        {
+            output["text"] = json!(self.theme.style(self.display_state(), text));
+        }
+        if let Some(task) = &status.task {
+            // Tasks may come from window titles, which often carry `&` and `<`.
+            let task = output::escape_markup(task);
+            append_tooltip(&mut output, format!("Task: {}", task));
+        }
+        if let Some(breakdown) = self.focus_breakdown() {
+            let breakdown = output::escape_markup(&breakdown);
+            append_tooltip(&mut output, format!("Focus: {}", breakdown));
         }
+        if self.captures > 0 {
+            append_tooltip(&mut output, format!("Captured: {}", self.captures));
+        }
+        if let Some((_, latest)) = self.notes.last() {
+            append_tooltip(
+                &mut output,
+                format!(
+                    "Note {}: {}",
+                    self.notes.len(),
+                    output::escape_markup(latest)
+                ),
+            );
+        }
+        if !self.rollover.is_zero() {
+            let minutes = self.rollover.as_secs() / 60;
+            append_tooltip(
+                &mut output,
+                format!("Rollover: +{} min for the long break", minutes),
+            );
+        } else if !self.long_break_bonus.is_zero() {
+            let minutes = self.long_break_bonus.as_secs() / 60;
+            append_tooltip(&mut output, format!("Rollover: {} min included", minutes));
+        }
+        if let Some(namespace) = &self.namespace {
+            let namespace = output::escape_markup(namespace);
+            append_tooltip(&mut output, format!("Namespace: {}", namespace));
+        }
+        if self.plan.current().is_some() {
+            let left = output::plural(self.plan.sessions_left(), "session");
+            append_tooltip(&mut output, format!("Plan: {} to go", left));
+        }
+        let redact = self.reporting.privacy == Privacy::RedactTasks;
+        for session in self.history.recent() {
+            let session = output::escape_markup(&session.describe(redact));
+            append_tooltip(&mut output, format!("Done: {}", session));
+        }
+        let color = self.theme.color(self.display_state());
+        let ctl = control::ctl_command(self.namespace.as_deref());
+        self.format.render(&output, color, &ctl)
+    }
+
+    /// The bar that `--signal` wakes up when the output changes.
+    pub fn bar_process(&self) -> &'static str {
+        self.format.bar_process()
     }
 
     pub fn load_pomodoro_state(&mut self) {
-        if let Ok(state_file) = File::open(STATE_PATH) {
+        if let Ok(state_file) = File::open(self.state_path()) {
             let state: serde_json::Value = serde_json::from_reader(state_file).unwrap_or_default();
-            self.start_time = state["start_time"]
-                .as_u64()
-                .map(|secs| Instant::now() - Duration::from_secs(secs));
-            self.end_time = state["end_time"]
-                .as_u64()
-                .map(|secs| Instant::now() + Duration::from_secs(secs));
-            self.total_time = state["total_time"].as_u64().unwrap_or(POMODORO_DURATION);
-            self.is_running = state["is_running"].as_bool().unwrap_or(false);
-            self.elapsed_time = state["elapsed_time"].as_u64().unwrap_or(0);
-            self.pomodoros_completed = state["pomodoros_completed"].as_u64().unwrap_or(0);
-        }
-    }
-
-    pub fn save_state(&self) {
-        let state_file = File::create(STATE_PATH).unwrap();
-        let state = json!({
+            self.restore(&state);
+        }
+    }
+
+    /// Picks the timer up from a state saved by [`Self::save_state`].
+    fn restore(&mut self, state: &serde_json::Value) {
+        self.start_time = state["start_time"]
+            .as_u64()
+            .map(|secs| Instant::now() - Duration::from_secs(secs));
+        self.end_time = state["end_time"]
+            .as_u64()
+            .map(|secs| Instant::now() + Duration::from_secs(secs));
+        self.total_time = state["total_time"]
+            .as_u64()
+            .map_or(self.work_duration, Duration::from_secs);
+        // State saved before the phase was recorded tells it only by its length.
+        self.phase = state["phase"]
+            .as_str()
+            .and_then(Phase::from_name)
+            .unwrap_or(if self.total_time == self.long_break_duration {
+                Phase::LongBreak
+            } else if self.total_time == self.short_break_duration {
+                Phase::ShortBreak
+            } else {
+                Phase::Work
+            });
+        self.is_running = state["is_running"].as_bool().unwrap_or(false);
+        self.elapsed_time = Duration::from_secs(state["elapsed_time"].as_u64().unwrap_or(0));
+        self.pomodoros_completed = state["pomodoros_completed"].as_u64().unwrap_or(0);
+        self.task = state["task"].as_str().map(str::to_string);
+        self.focus_time = state["focus_time"]
+            .as_object()
+            .map(|apps| {
+                apps.iter()
+                    .filter_map(|(app, secs)| {
+                        Some((app.clone(), Duration::from_secs(secs.as_u64()?)))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.schedule.scheduled_start = state["scheduled_start"]
+            .as_u64()
+            .map(|secs| Instant::now() + Duration::from_secs(secs));
+        self.breaks = BreakCounters::from_json(&state["breaks"]);
+        self.captures = state["captures"].as_u64().unwrap_or(0);
+        self.adjustment = state["adjustment"].as_i64().unwrap_or(0);
+        // A different day is noticed on the first tick, which voids a stale session.
+        if let Some(today) = state["today"].as_str() {
+            self.goal.today = today.to_string();
+            self.goal.completed_today = state["completed_today"].as_u64().unwrap_or(0);
+            self.history = History::from_json(&state["history"]);
+        }
+        self.goal.streak = state["streak"].as_u64().unwrap_or(0);
+        self.goal.goal_met_on = state["goal_met_on"].as_str().map(str::to_string);
+        self.session_started = state["session_started"].as_str().map(str::to_string);
+        self.reporting.phase_started = state["phase_started"].as_str().map(str::to_string);
+        self.reporting.session_id = state["session_id"].as_str().map(str::to_string);
+        self.plan = Plan::from_json(&state["plan"]);
+        self.planned = state["planned"].as_bool().unwrap_or(false);
+        self.notes = state["notes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|note| {
+                let at = note["at"].as_str()?.to_string();
+                Some((at, note["text"].as_str()?.to_string()))
+            })
+            .collect();
+        self.rollover = Duration::from_secs(state["rollover"].as_u64().unwrap_or(0));
+        self.long_break_bonus =
+            Duration::from_secs(state["long_break_bonus"].as_u64().unwrap_or(0));
+        let after = |secs: &serde_json::Value| {
+            secs.as_u64()
+                .map(|secs| Instant::now() + Duration::from_secs(secs))
+        };
+        self.schedule.warmup_until = after(&state["warmup_until"]);
+        self.schedule.ready_until = after(&state["ready_until"]);
+        self.reminders.paused_since = state["paused_since"]
+            .as_u64()
+            .map(|secs| Instant::now() - Duration::from_secs(secs));
+        self.alerts.deferred_event = state["deferred_event"]
+            .as_str()
+            .and_then(PomodoroEvent::from_name);
+        self.reporting.errors = ErrorLog::from_json(&state["errors"]);
+    }
+
+    /// Writes the state file that [`Self::load_pomodoro_state`] picks up, e.g. after `upgrade`.
+    pub fn save_state(&self) -> Result<(), Failure> {
+        let path = self.state_path();
+        let failed = |err: &dyn std::fmt::Display| {
+            Failure::new("state", format!("{}: {}", path.display(), err))
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| failed(&err))?;
+        }
+        let state_file = File::create(&path).map_err(|err| failed(&err))?;
+        serde_json::to_writer_pretty(state_file, &self.state()).map_err(|err| failed(&err))
+    }
+
+    /// What [`Self::save_state`] writes, with deadlines as the seconds left until them.
+    fn state(&self) -> serde_json::Value {
+        let until = |time: Option<Instant>| {
+            time.map(|time| time.saturating_duration_since(Instant::now()).as_secs())
+        };
+        json!({
             "start_time": self.start_time.map(|t| t.elapsed().as_secs()),
             "end_time": self.end_time.map(|t| t.duration_since(Instant::now()).as_secs()),
-            "total_time": self.total_time,
+            "phase": self.phase_name(),
+            "total_time": self.total_time.as_secs(),
+            "adjustment": self.adjustment,
             "is_running": self.is_running,
-            "elapsed_time": self.elapsed_time,
-            "pomodoros_completed": self.pomodoros_completed
-        });
-        serde_json::to_writer_pretty(state_file, &state).unwrap();
+            "elapsed_time": self.elapsed_time.as_secs(),
+            "pomodoros_completed": self.pomodoros_completed,
+            "task": self.task,
+            "focus_time": self
+                .focus_time
+                .iter()
+                .map(|(app, time)| (app.clone(), json!(time.as_secs())))
+                .collect::<serde_json::Map<_, _>>(),
+            "scheduled_start": until(self.schedule.scheduled_start),
+            "breaks": self.breaks.to_json(),
+            "rollover": self.rollover.as_secs(),
+            "notes": self.notes_json(),
+            "captures": self.captures,
+            "long_break_bonus": self.long_break_bonus.as_secs(),
+            "today": self.goal.today,
+            "completed_today": self.goal.completed_today,
+            "streak": self.goal.streak,
+            "goal_met_on": self.goal.goal_met_on,
+            "session_started": self.session_started,
+            "phase_started": self.reporting.phase_started,
+            "session_id": self.reporting.session_id,
+            "history": self.history.to_json(),
+            "plan": self.plan.to_json(),
+            "planned": self.planned,
+            "warmup_until": until(self.schedule.warmup_until),
+            "ready_until": until(self.schedule.ready_until),
+            "paused_since": self.reminders.paused_since.map(|t| t.elapsed().as_secs()),
+            "deferred_event": self.alerts.deferred_event.map(PomodoroEvent::name),
+            "errors": self.reporting.errors.to_json()
+        })
+    }
+}
+
+impl Pomodoro {
+    /// The full engine state, including derived deadlines and the configuration in effect.
+    pub fn dump(&self) -> serde_json::Value {
+        let now = Instant::now();
+        let status = self.snapshot();
+        json!({
+            "status": status,
+            "total_time_secs": self.phase_length().as_secs(),
+            "phase_ends_in_secs": status.running.then_some(status.remaining.as_secs()),
+            "grace_ends_in_secs": self
+                .schedule.ready_until
+                .filter(|_| self.in_grace())
+                .map(|t| t.saturating_duration_since(now).as_secs()),
+            "warmup_ends_in_secs": self
+                .schedule.warmup_until
+                .map(|t| t.saturating_duration_since(now).as_secs()),
+            "scheduled_start_in_secs": self
+                .schedule.scheduled_start
+                .map(|t| t.saturating_duration_since(now).as_secs()),
+            "breaks": self.breaks.to_json(),
+            "latency": latency::to_json(),
+            "rollover_secs": self.rollover.as_secs(),
+            "long_break_bonus_secs": self.long_break_bonus.as_secs(),
+            "notes": self.notes_json(),
+            "captures": self.captures,
+            "focus_time_secs": self
+                .focus_time
+                .iter()
+                .map(|(app, time)| (app.clone(), json!(time.as_secs())))
+                .collect::<serde_json::Map<_, _>>(),
+            "recent_errors": self
+                .reporting.errors
+                .iter()
+                .map(|failure| format!("{}: {}", failure.code, failure.message))
+                .collect::<Vec<_>>(),
+            "config": self.config_json(),
+        })
+    }
+
+    /// The settings the timer was started with, for `dump`.
+    fn config_json(&self) -> serde_json::Value {
+        json!({
+        "work_secs": self.work_duration.as_secs(),
+        "short_break_secs": self.short_break_duration.as_secs(),
+        "long_break_secs": self.long_break_duration.as_secs(),
+        "pomodoros_per_long_break": self.pomodoros_per_long_break,
+        "sounds": {
+            "default": self.alerts.sounds.default,
+            "work": self.alerts.sounds.work,
+            "short_break": self.alerts.sounds.short_break,
+            "long_break": self.alerts.sounds.long_break,
+        },
+        "normalize_sound": self.alerts.normalize_sound,
+        "player": format!("{:?}", self.alerts.player),
+        "namespace": self.namespace,
+        "state_path": self.state_path(),
+        "inbox": self.inbox,
+        "task_source": self.task_source.as_ref().map(|source| format!("{:?}", source)),
+        "audit_path": self.reporting.audit.path(),
+        "auto_task": self.auto_task,
+        "quiet_fullscreen": self.alerts.quiet_fullscreen,
+        "mic_probe": self.alerts.mic_probe.map(|probe| format!("{:?}", probe)),
+        "warmup_secs": self.schedule.warmup.as_secs(),
+        "grace_secs": self.schedule.grace.as_secs(),
+        "rollover_cap_secs": self.rollover_cap.as_secs(),
+        "focus_interval_secs": self.focus_interval.map(|interval| interval.as_secs()),
+        "distractions": self.reminders.distractions,
+        "distraction_limit_secs": self.reminders.distraction_limit.as_secs(),
+        "theme": format!("{:?}", self.theme),
+        "output_style": format!("{:?}", self.output_style),
+        "format": format!("{:?}", self.format),
+        "show_icons": self.show_icons,
+        "daily_goal": self.goal.daily_goal,
+        "auto_start_breaks": self.schedule.auto_start_breaks,
+        "auto_start_work": self.schedule.auto_start_work,
+        "overtime": self.overtime,
+        "privacy": format!("{:?}", self.reporting.privacy),
+        "on_suspend": format!("{:?}", self.on_suspend),
+        "format_text": self.format_text.as_ref().map(|template| format!("{:?}", template)),
+        "speak_at_secs": self
+            .alerts.speak_at
+            .iter()
+            .map(|milestone| milestone.as_secs())
+            .collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Serializes the same snapshot as [`Pomodoro::dump`].
+impl Serialize for Pomodoro {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.dump().serialize(serializer)
+    }
+}
+
+/// A one-line summary such as `work 12:34 remaining (running), 2/4 pomodoros`.
+impl fmt::Display for Pomodoro {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.snapshot().fmt(f)
+    }
+}
+
+/// `$XDG_STATE_HOME/waybar-pomodoro/<name>`, or `pomodoro_<name>` in the working directory
+/// when there is no state directory.
+pub fn state_file(name: &str) -> PathBuf {
+    match dirs::state_dir() {
+        Some(dir) => dir.join("waybar-pomodoro").join(name),
+        None => PathBuf::from(format!("pomodoro_{}", name)),
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/waybar-pomodoro/<name>`, or the same under `$XDG_CACHE_HOME`, for files
+/// written out for other programs to open. The directory is kept private to the user, so no
+/// one else can plant a symlink where the file is about to be written.
+pub fn scratch_file(name: &str) -> Result<PathBuf, String> {
+    let dir = dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .ok_or("There is no runtime or cache directory")?
+        .join("waybar-pomodoro");
+    let failed = |err: std::io::Error| format!("{}: {}", dir.display(), err);
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .map_err(failed)?;
+    let metadata = fs::symlink_metadata(&dir).map_err(failed)?;
+    if !metadata.is_dir() || metadata.uid() != nix::unistd::getuid().as_raw() {
+        return Err(format!("{} is not a directory of ours", dir.display()));
+    }
+    if metadata.mode() & 0o077 != 0 {
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700)).map_err(failed)?;
+    }
+    Ok(dir.join(name))
+}
+
+/// `$XDG_DATA_HOME/waybar-pomodoro/inbox.txt`, or `inbox.txt` in the working directory.
+fn default_inbox() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join("waybar-pomodoro"))
+        .unwrap_or_default()
+        .join("inbox.txt")
+}
+
+/// How much of the phase has passed, from 0 to 100.
+fn percentage(elapsed: Duration, total: Duration) -> u64 {
+    if total.is_zero() {
+        return 0;
+    }
+    (elapsed.as_secs() * 100 / total.as_secs()).min(100)
+}
+
+/// Adds a line to the tooltip of a waybar output object.
+fn append_tooltip(output: &mut serde_json::Value, line: String) {
+    output["tooltip"] = match output["tooltip"].as_str() {
+        Some(tooltip) => json!(format!("{}\n{}", tooltip, line)),
+        None => json!(line),
+    };
+}
+
+/// Formats a duration as `MM:SS`, dropping sub-second precision.
+pub fn format_minutes(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::headless;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A timer with phases of the given minutes that logs its notifications instead of sending
+    /// them, and keeps its audit and history logs in a directory of its own.
+    pub fn timer(work: u64, short_break: u64, long_break: u64) -> Pomodoro {
+        static TIMERS: AtomicUsize = AtomicUsize::new(0);
+        headless::enable();
+        let minutes = |count: u64| Duration::from_secs(count * 60);
+        let mut pomodoro = Pomodoro::builder()
+            .work(minutes(work))
+            .short_break(minutes(short_break))
+            .long_break(minutes(long_break))
+            .build()
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!(
+            "waybar-pomodoro-tests-{}-{}",
+            std::process::id(),
+            TIMERS.fetch_add(1, Ordering::Relaxed)
+        ));
+        pomodoro.reporting.audit = AuditLog::at(dir.join("audit.log"));
+        pomodoro.reporting.history_log = HistoryLog::at(dir.join("history.jsonl"));
+        pomodoro
+    }
+
+    /// The phases a fresh timer goes through over two long-break cycles, ending each one with
+    /// `end_phase`.
+    fn phases(mut pomodoro: Pomodoro, end_phase: fn(&mut Pomodoro)) -> Vec<Phase> {
+        (0..8)
+            .map(|_| {
+                end_phase(&mut pomodoro);
+                pomodoro.phase
+            })
+            .collect()
+    }
+
+    const CYCLE: [Phase; 8] = [
+        Phase::ShortBreak,
+        Phase::Work,
+        Phase::ShortBreak,
+        Phase::Work,
+        Phase::ShortBreak,
+        Phase::Work,
+        Phase::LongBreak,
+        Phase::Work,
+    ];
+
+    fn run_out(pomodoro: &mut Pomodoro) {
+        pomodoro.start_phase();
+        pomodoro.handle_elapsed_time_over_total_time(pomodoro.phase_length());
+    }
+
+    fn skip(pomodoro: &mut Pomodoro) {
+        pomodoro.skip(Cause::Command);
+    }
+
+    #[test]
+    fn phases_that_run_out_follow_the_cycle() {
+        let _local_time = clock::tests::lock_local_time();
+        assert_eq!(phases(timer(25, 5, 15), run_out), CYCLE);
+    }
+
+    #[test]
+    fn phases_follow_the_cycle_when_work_is_as_long_as_a_break() {
+        let _local_time = clock::tests::lock_local_time();
+        assert_eq!(phases(timer(5, 5, 15), run_out), CYCLE);
+        assert_eq!(phases(timer(15, 5, 15), run_out), CYCLE);
+        assert_eq!(phases(timer(5, 5, 15), skip), CYCLE);
+        assert_eq!(phases(timer(15, 5, 15), skip), CYCLE);
+    }
+
+    #[test]
+    fn each_phase_gets_its_own_length_when_lengths_are_equal() {
+        let _local_time = clock::tests::lock_local_time();
+        let mut pomodoro = timer(5, 5, 15);
+        for phase in CYCLE {
+            skip(&mut pomodoro);
+            assert_eq!(pomodoro.phase, phase);
+            let minutes = match phase {
+                Phase::Work | Phase::ShortBreak => 5,
+                Phase::LongBreak => 15,
+            };
+            assert_eq!(pomodoro.phase_length(), Duration::from_secs(minutes * 60));
+        }
+    }
+
+    #[test]
+    fn adjustments_too_large_to_keep_track_of_are_refused() {
+        let _local_time = clock::tests::lock_local_time();
+        let mut pomodoro = timer(25, 5, 15);
+        pomodoro.start_phase();
+        assert!(pomodoro.adjust(i64::MAX).is_none());
+        assert!(pomodoro.adjust(60).is_some());
+        assert_eq!(pomodoro.phase_length(), Duration::from_secs(26 * 60));
+        assert!(pomodoro.adjust(i64::MIN).is_some());
+        assert_eq!(pomodoro.phase_length(), Duration::ZERO);
+        assert!(pomodoro.adjust(i64::MIN).is_none());
+    }
+
+    #[test]
+    fn saved_state_keeps_what_is_in_flight() {
+        let _local_time = clock::tests::lock_local_time();
+        let mut saved = timer(25, 5, 15);
+        let now = Instant::now();
+        saved.schedule.warmup_until = Some(now + Duration::from_secs(20));
+        saved.schedule.ready_until = Some(now + Duration::from_secs(90));
+        saved.reminders.paused_since = Some(now - Duration::from_secs(300));
+        saved.alerts.deferred_event = Some(PomodoroEvent::ShortBreak);
+        saved.report(Failure::new("sound", "aplay: not found"));
+
+        let mut restored = timer(25, 5, 15);
+        restored.restore(&saved.state());
+        // Saved to the second, so deadlines may come back a second early.
+        let left = |time: Option<Instant>| time.unwrap().duration_since(now).as_secs();
+        assert!((19..=20).contains(&left(restored.schedule.warmup_until)));
+        assert!((89..=90).contains(&left(restored.schedule.ready_until)));
+        let paused = restored.reminders.paused_since.unwrap().elapsed().as_secs();
+        assert!((300..=301).contains(&paused));
+        assert!(matches!(
+            restored.alerts.deferred_event,
+            Some(PomodoroEvent::ShortBreak)
+        ));
+        let failures: Vec<_> = restored
+            .reporting
+            .errors
+            .iter()
+            .map(|failure| format!("{}: {}", failure.code, failure.message))
+            .collect();
+        assert_eq!(failures, ["sound: aplay: not found"]);
+    }
+
+    #[test]
+    fn notes_are_logged_with_the_session_and_reported() {
+        let _local_time = clock::tests::lock_local_time();
+        let mut pomodoro = timer(25, 5, 15);
+        pomodoro.start_phase();
+        pomodoro.task = Some("write report".to_string());
+        assert!(pomodoro.add_note("call back Alex"));
+        assert!(pomodoro.add_note("idea: batch the emails"));
+        pomodoro.skip(Cause::Command);
+
+        let report = pomodoro.daily_report().unwrap();
+        let lines: Vec<_> = report.lines().collect();
+        assert_eq!(lines.len(), 3, "{}", report);
+        assert!(lines[0].ends_with(" write report (aborted)"), "{}", report);
+        assert!(lines[1].starts_with("  ") && lines[1].ends_with(" call back Alex"));
+        assert!(lines[2].ends_with(" idea: batch the emails"));
+
+        pomodoro.reporting.privacy = Privacy::RedactTasks;
+        let redacted = pomodoro.daily_report().unwrap();
+        assert!(!redacted.contains("write report") && redacted.contains("call back Alex"));
+    }
+
+    /// The phase a state file saved before the phase was recorded resolves to.
+    fn legacy_phase(pomodoro: &mut Pomodoro, total_minutes: u64) -> Phase {
+        pomodoro.restore(&json!({
+            "total_time": total_minutes * 60,
+            "elapsed_time": 120,
+            "is_running": false,
+            "pomodoros_completed": 1,
+        }));
+        pomodoro.phase
+    }
+
+    #[test]
+    fn legacy_state_with_a_work_length_is_work() {
+        let _local_time = clock::tests::lock_local_time();
+        assert_eq!(legacy_phase(&mut timer(25, 5, 15), 25), Phase::Work);
+    }
+
+    #[test]
+    fn legacy_state_with_a_break_length_is_that_break() {
+        let _local_time = clock::tests::lock_local_time();
+        assert_eq!(legacy_phase(&mut timer(25, 5, 15), 5), Phase::ShortBreak);
+        assert_eq!(legacy_phase(&mut timer(25, 5, 15), 15), Phase::LongBreak);
+    }
+
+    #[test]
+    fn legacy_state_with_equal_lengths_is_read_as_a_break() {
+        let _local_time = clock::tests::lock_local_time();
+        // Old versions could not tell either, and took a matching length for a break.
+        assert_eq!(legacy_phase(&mut timer(5, 5, 15), 5), Phase::ShortBreak);
+        assert_eq!(legacy_phase(&mut timer(15, 5, 15), 15), Phase::LongBreak);
+    }
+
+    #[test]
+    fn recorded_phase_wins_over_equal_lengths() {
+        let _local_time = clock::tests::lock_local_time();
+        let mut pomodoro = timer(5, 5, 15);
+        pomodoro.restore(&json!({ "phase": "work", "total_time": 300 }));
+        assert_eq!(pomodoro.phase, Phase::Work);
+        pomodoro.restore(&json!({ "phase": "short-break", "total_time": 300 }));
+        assert_eq!(pomodoro.phase, Phase::ShortBreak);
     }
 }
@@ -0,0 +1,84 @@
+use crate::clock;
+
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A recurring start time such as `Mon-Fri 09:05`.
+#[derive(Clone, Debug)]
+struct Rule {
+    days: [bool; 7], // Indexed like `tm_wday`, Sunday first
+    hour: i32,
+    minute: i32,
+}
+
+impl Rule {
+    fn parse(spec: &str) -> Option<Self> {
+        let (days_spec, time) = match spec.trim().split_once(' ') {
+            Some((days, time)) => (days, time.trim()),
+            None => ("daily", spec.trim()),
+        };
+        let (hour, minute) = time.split_once(':')?;
+        let hour: i32 = hour.parse().ok()?;
+        let minute: i32 = minute.parse().ok()?;
+        if !(0..24).contains(&hour) || !(0..60).contains(&minute) {
+            return None;
+        }
+
+        let mut days = [false; 7];
+        for part in days_spec.to_lowercase().split(',') {
+            if part == "daily" || part == "*" {
+                days = [true; 7];
+            } else if let Some((from, to)) = part.split_once('-') {
+                let mut day = weekday(from)?;
+                let to = weekday(to)?;
+                days[day] = true;
+                while day != to {
+                    day = (day + 1) % 7;
+                    days[day] = true;
+                }
+            } else {
+                days[weekday(part)?] = true;
+            }
+        }
+        Some(Self { days, hour, minute })
+    }
+
+    fn matches(&self, tm: &libc::tm) -> bool {
+        self.days[tm.tm_wday as usize] && self.hour == tm.tm_hour && self.minute == tm.tm_min
+    }
+}
+
+fn weekday(name: &str) -> Option<usize> {
+    let name = name.get(..3)?;
+    WEEKDAYS.iter().position(|day| *day == name)
+}
+
+/// Recurring automatic starts configured with `--auto-start`.
+#[derive(Clone, Debug, Default)]
+pub struct AutoStart {
+    rules: Vec<Rule>,
+    last_fired: Option<(i32, i32, i32)>, // Year, day of year and minute of the last start
+}
+
+impl AutoStart {
+    pub fn new(specs: &[String]) -> Result<Self, String> {
+        let rules = specs
+            .iter()
+            .map(|spec| Rule::parse(spec).ok_or_else(|| format!("Invalid schedule {}", spec)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            rules,
+            last_fired: None,
+        })
+    }
+
+    /// Returns true once when the local time reaches one of the rules.
+    pub fn due(&mut self) -> bool {
+        let tm = clock::local_now();
+        let minute = (tm.tm_year, tm.tm_yday, tm.tm_hour * 60 + tm.tm_min);
+        if self.last_fired == Some(minute) || !self.rules.iter().any(|rule| rule.matches(&tm)) {
+            return false;
+        }
+        self.last_fired = Some(minute);
+        true
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io::Write,
+    os::unix::io::{FromRawFd, RawFd},
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use crate::{errors::Failure, headless, pomodoro::format_minutes};
+
+/// The timer's state as reported outside the engine. Command replies, the state dump and
+/// the metrics textfile all read it from here, so they cannot disagree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Status {
+    pub phase: &'static str, // `work`, `short-break` or `long-break`
+    pub remaining: Duration,
+    pub elapsed: Duration,
+    pub running: bool,
+    pub cycle: u64,              // Pomodoros completed towards the long break
+    pub cycle_length: u64,       // Pomodoros before each long break
+    pub completed_today: u64,    // Work sessions completed today
+    pub goal: Option<u64>,       // Work sessions to complete each day, if there is a goal
+    pub streak: Option<u64>,     // Days in a row the goal was met, if there is a goal
+    pub task: Option<String>,    // What the current work session is about
+    pub session: Option<String>, // UUID of the current phase, once it has started
+}
+
+impl Serialize for Status {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut status = serializer.serialize_struct("Status", 11)?;
+        status.serialize_field("phase", self.phase)?;
+        status.serialize_field("remaining_secs", &self.remaining.as_secs())?;
+        status.serialize_field("elapsed_secs", &self.elapsed.as_secs())?;
+        status.serialize_field("running", &self.running)?;
+        status.serialize_field("cycle", &self.cycle)?;
+        status.serialize_field("cycle_length", &self.cycle_length)?;
+        status.serialize_field("completed_today", &self.completed_today)?;
+        status.serialize_field("goal", &self.goal)?;
+        status.serialize_field("streak", &self.streak)?;
+        status.serialize_field("task", &self.task)?;
+        status.serialize_field("session", &self.session)?;
+        status.end()
+    }
+}
+
+/// What the timer keeps to itself when it reports its state.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Privacy {
+    #[default]
+    Off,
+    RedactTasks, // Leave task labels out of replies, dumps and the daemon's log
+}
+
+impl FromStr for Privacy {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "off" => Ok(Privacy::Off),
+            "redact-tasks" => Ok(Privacy::RedactTasks),
+            _ => Err(format!("Unknown privacy setting {}", name)),
+        }
+    }
+}
+
+/// A one-line summary such as `work 12:34 remaining (running), 2/4 pomodoros`.
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} remaining ({}), {}/{} pomodoros",
+            self.phase,
+            format_minutes(self.remaining),
+            if self.running { "running" } else { "paused" },
+            self.cycle,
+            self.cycle_length
+        )
+    }
+}
+
+/// Mirrors the latest status line into a file that scripts can watch with inotify.
+pub struct StatusFile {
+    path: PathBuf,
+    last: String, // Status written last, so unchanged ticks don't touch the file
+}
+
+impl StatusFile {
+    /// `$XDG_RUNTIME_DIR/waybar-pomodoro/status.json`, or `status.<namespace>.json`.
+    pub fn new(namespace: Option<&str>) -> Option<Self> {
+        let name = match namespace {
+            Some(namespace) => format!("status.{}.json", namespace),
+            None => "status.json".to_string(),
+        };
+        let path = dirs::runtime_dir()?.join("waybar-pomodoro").join(name);
+        Some(Self {
+            path,
+            last: String::new(),
+        })
+    }
+
+    /// Replaces the file in one step, so watchers never read a half-written status.
+    pub fn write(&mut self, status: &str) -> Result<(), Failure> {
+        if status == self.last {
+            return Ok(());
+        }
+        let temporary = self.path.with_extension("json.tmp");
+        self.path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&temporary, format!("{}\n", status)))
+            .and_then(|_| fs::rename(&temporary, &self.path))
+            .map_err(|err| {
+                Failure::new("status-file", format!("{}: {}", self.path.display(), err))
+            })?;
+        self.last = status.to_string();
+        Ok(())
+    }
+}
+
+/// Writes the status lines to a descriptor inherited from the parent instead of stdout, for a
+/// wrapper that reads several sources through one process.
+pub struct StatusFd {
+    fd: RawFd,
+    file: File,
+}
+
+impl StatusFd {
+    /// Takes over `fd`, which the parent must have left open.
+    pub fn open(fd: RawFd) -> Result<Self, String> {
+        // SAFETY: `F_GETFD` only reads the descriptor's flags.
+        if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+            return Err(format!("--status-fd {} is not an open file descriptor", fd));
+        }
+        // SAFETY: the descriptor is open, and nothing else in the process uses it.
+        let file = unsafe { File::from_raw_fd(fd) };
+        Ok(Self { fd, file })
+    }
+
+    pub fn write(&mut self, status: &str) -> Result<(), Failure> {
+        writeln!(self.file, "{}", status)
+            .and_then(|_| self.file.flush())
+            .map_err(|err| Failure::new("status-fd", format!("fd {}: {}", self.fd, err)))
+    }
+}
+
+/// Sends SIGRTMIN+`offset` to every running `bar` (waybar or i3blocks), which makes a module or
+/// block with that `signal` run its command again.
+pub fn signal_bar(bar: &str, offset: i32) -> Result<(), Failure> {
+    if headless::skip("signal", &format!("SIGRTMIN+{} to {}", offset, bar)) {
+        return Ok(());
+    }
+    let failed = |err: std::io::Error| Failure::new("signal", format!("/proc: {}", err));
+    for entry in fs::read_dir("/proc").map_err(failed)?.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|pid| pid.parse().ok()) else {
+            continue;
+        };
+        let comm = fs::read_to_string(entry.path().join("comm")).unwrap_or_default();
+        if comm.trim_end() == bar {
+            // SAFETY: `kill` only sends a signal; a process that exited meanwhile is skipped.
+            unsafe {
+                libc::kill(pid, libc::SIGRTMIN() + offset);
+            }
+        }
+    }
+    Ok(())
+}
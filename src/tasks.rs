@@ -0,0 +1,153 @@
+use std::{fs, path::PathBuf, process::Command, str::FromStr};
+
+use serde_json::Value;
+
+use crate::{clock, headless};
+
+/// Where to look for the next task when a session starts without one.
+#[derive(Clone, Debug)]
+pub enum TaskSource {
+    Taskwarrior,      // The most urgent pending task from `task export`
+    TodoTxt(PathBuf), // The highest-priority open line of a todo.txt file
+}
+
+impl FromStr for TaskSource {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source {
+            "taskwarrior" => Ok(TaskSource::Taskwarrior),
+            "" => Err("Empty task source".to_string()),
+            path => Ok(TaskSource::TodoTxt(PathBuf::from(path))),
+        }
+    }
+}
+
+impl TaskSource {
+    /// The best candidate to work on next, if there is one.
+    pub fn next_task(&self) -> Option<String> {
+        match self {
+            TaskSource::Taskwarrior => taskwarrior_next(),
+            TaskSource::TodoTxt(path) => todo_txt_next(&fs::read_to_string(path).ok()?),
+        }
+    }
+
+    /// Closes the open task with this description. Returns false if there is none.
+    pub fn complete(&self, task: &str) -> Result<bool, String> {
+        if headless::skip("task done", task) {
+            return Ok(false);
+        }
+        match self {
+            TaskSource::Taskwarrior => taskwarrior_complete(task),
+            TaskSource::TodoTxt(path) => {
+                let todo = fs::read_to_string(path)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+                let Some(todo) = todo_txt_complete(&todo, task) else {
+                    return Ok(false);
+                };
+                fs::write(path, todo).map_err(|err| format!("{}: {}", path.display(), err))?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+fn taskwarrior_next() -> Option<String> {
+    let output = Command::new("task")
+        .args(["rc.verbose=nothing", "status:pending", "export"])
+        .output()
+        .ok()?;
+    let tasks: Value = serde_json::from_slice(&output.stdout).ok()?;
+    tasks
+        .as_array()?
+        .iter()
+        .filter(|task| task["description"].is_string())
+        .max_by(|a, b| {
+            let urgency = |task: &Value| task["urgency"].as_f64().unwrap_or(0.0);
+            urgency(a).total_cmp(&urgency(b))
+        })
+        .and_then(|task| task["description"].as_str())
+        .map(str::to_string)
+}
+
+/// Marks the pending task with this exact description done, by its uuid.
+fn taskwarrior_complete(task: &str) -> Result<bool, String> {
+    let output = Command::new("task")
+        .args(["rc.verbose=nothing", "status:pending", "export"])
+        .output()
+        .map_err(|err| format!("Failed to run task: {}", err))?;
+    let tasks: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|err| format!("Invalid task export: {}", err))?;
+    let uuid = tasks
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|pending| pending["description"] == task)
+        .and_then(|pending| pending["uuid"].as_str());
+    let Some(uuid) = uuid else {
+        return Ok(false);
+    };
+    let status = Command::new("task")
+        .args(["rc.verbose=nothing", "rc.confirmation=off", uuid, "done"])
+        .status()
+        .map_err(|err| format!("Failed to run task: {}", err))?;
+    if status.success() {
+        Ok(true)
+    } else {
+        Err("task done exited with an error".to_string())
+    }
+}
+
+/// Marks the first open line with this description done, as `x <date> <line>` with the
+/// priority dropped. Returns the new file, or None if no line matched.
+fn todo_txt_complete(todo: &str, task: &str) -> Option<String> {
+    let mut lines: Vec<String> = todo.lines().map(str::to_string).collect();
+    let line = lines.iter_mut().find(|line| {
+        let line = line.trim();
+        !line.is_empty() && !line.starts_with("x ") && description(line) == task
+    })?;
+    let open = line.trim();
+    let open = if priority(open).is_some() {
+        &open[4..]
+    } else {
+        open
+    };
+    *line = format!("x {} {}", clock::local_date(), open);
+    let mut todo = lines.join("\n");
+    todo.push('\n');
+    Some(todo)
+}
+
+/// Picks the open task with the highest `(A)`-style priority, the first one on ties.
+fn todo_txt_next(todo: &str) -> Option<String> {
+    todo.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("x "))
+        .enumerate()
+        .min_by_key(|(index, line)| (priority(line).unwrap_or(char::MAX), *index))
+        .map(|(_, line)| description(line))
+}
+
+fn priority(line: &str) -> Option<char> {
+    let bytes = line.as_bytes();
+    let is_priority = bytes.len() > 3
+        && bytes[0] == b'('
+        && bytes[1].is_ascii_uppercase()
+        && bytes[2] == b')'
+        && bytes[3] == b' ';
+    is_priority.then(|| char::from(bytes[1]))
+}
+
+/// The task text without its priority and creation date.
+fn description(line: &str) -> String {
+    let mut rest = line;
+    if priority(rest).is_some() {
+        rest = &rest[4..];
+    }
+    let is_date =
+        |word: &str| word.len() == 10 && word.chars().all(|c| c.is_ascii_digit() || c == '-');
+    match rest.split_once(' ') {
+        Some((date, text)) if is_date(date) => text.to_string(),
+        _ => rest.to_string(),
+    }
+}
@@ -0,0 +1,75 @@
+use std::str::FromStr;
+
+/// Preset Pango styling of the bar text, so modules are readable without custom CSS.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Theme {
+    #[default]
+    None, // Plain text; style it through waybar CSS instead
+    Classic,
+    Colorblind, // Okabe-Ito palette, distinguishable with the common color vision deficiencies
+    Monochrome, // No colors at all, only weight and style
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "none" => Ok(Theme::None),
+            "classic" => Ok(Theme::Classic),
+            "colorblind" => Ok(Theme::Colorblind),
+            "monochrome" => Ok(Theme::Monochrome),
+            _ => Err(format!("Unknown theme {}", name)),
+        }
+    }
+}
+
+impl Theme {
+    /// The color of a timer state such as `work`, `paused` or `error`, for themes that have one.
+    pub fn color(self, state: &str) -> Option<&'static str> {
+        match (self, state) {
+            (Theme::None | Theme::Monochrome, _) => None,
+            (Theme::Classic, "work" | "overtime") => Some("#e06c75"),
+            (Theme::Classic, "short-break") => Some("#98c379"),
+            (Theme::Classic, "long-break") => Some("#61afef"),
+            (Theme::Classic, "scheduled" | "warmup") => Some("#e5c07b"),
+            (Theme::Classic, "error") => Some("#ff5555"),
+            (Theme::Classic, _) => Some("#abb2bf"),
+            (Theme::Colorblind, "work" | "overtime") => Some("#D55E00"),
+            (Theme::Colorblind, "short-break") => Some("#009E73"),
+            (Theme::Colorblind, "long-break") => Some("#0072B2"),
+            (Theme::Colorblind, "scheduled" | "warmup") => Some("#E69F00"),
+            (Theme::Colorblind, "error") => Some("#CC79A7"),
+            (Theme::Colorblind, _) => Some("#999999"),
+        }
+    }
+
+    /// Span attributes for a timer state.
+    fn attributes(self, state: &str) -> Option<String> {
+        if let Some(color) = self.color(state) {
+            let weight = if state == "error" {
+                r#" weight="bold""#
+            } else {
+                ""
+            };
+            return Some(format!(r#"foreground="{}"{}"#, color, weight));
+        }
+        let attributes = match (self, state) {
+            (Theme::Monochrome, "work") => r#"weight="bold""#,
+            (Theme::Monochrome, "short-break" | "long-break") => r#"style="italic""#,
+            (Theme::Monochrome, "scheduled" | "warmup") => r#"underline="single""#,
+            (Theme::Monochrome, "error") => r#"weight="heavy" underline="double""#,
+            (Theme::Monochrome, _) => r#"alpha="60%""#,
+            _ => return None,
+        };
+        Some(attributes.to_string())
+    }
+
+    /// Wraps the text in Pango markup for the given state.
+    pub fn style(self, state: &str, text: &str) -> String {
+        match self.attributes(state) {
+            Some(attributes) => format!("<span {}>{}</span>", attributes, text),
+            None => text.to_string(),
+        }
+    }
+}
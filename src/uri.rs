@@ -0,0 +1,104 @@
+use crate::clock;
+
+/// Translates a `pomodoro://` link into the commands to send to the timer, e.g.
+/// `pomodoro://start?task=Write%20report&duration=50m` into `task Write report` and
+/// `start --for 50m`.
+pub fn commands(uri: &str) -> Result<Vec<String>, String> {
+    let link = uri
+        .strip_prefix("pomodoro://")
+        .ok_or_else(|| format!("Not a pomodoro:// link: {}", uri))?;
+    let (action, query) = link.split_once('?').unwrap_or((link, ""));
+    let action = action.trim_end_matches('/');
+
+    let mut task = None;
+    let mut duration = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = decode(value).ok_or_else(|| format!("Invalid escape in {}", pair))?;
+        // Each line sent to the timer is a command of its own, so a newline in a value could
+        // smuggle in another command such as `stop`.
+        if value.contains(char::is_control) {
+            return Err(format!("Control characters in {}", key));
+        }
+        match key {
+            "task" => task = Some(value),
+            "duration" => duration = Some(value),
+            _ => return Err(format!("Unknown parameter {}", key)),
+        }
+    }
+
+    match action {
+        "start" => {
+            let mut commands = Vec::new();
+            if let Some(task) = task.filter(|task| !task.trim().is_empty()) {
+                commands.push(format!("task {}", task.trim()));
+            }
+            commands.push(match duration {
+                Some(duration)
+                    if clock::parse_duration(&duration).is_some_and(|d| !d.is_zero()) =>
+                {
+                    format!("start --for {}", duration.trim())
+                }
+                Some(duration) => return Err(format!("Invalid duration {}", duration)),
+                None => "start".to_string(),
+            });
+            Ok(commands)
+        }
+        "pause" | "toggle" | "skip" if task.is_none() && duration.is_none() => {
+            Ok(vec![action.to_string()])
+        }
+        "pause" | "toggle" | "skip" => Err(format!("{} takes no parameters", action)),
+        _ => Err(format!("Unknown action {}", action)),
+    }
+}
+
+/// Decodes `%XX` escapes and `+` for spaces, as browsers write query values.
+fn decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut rest = value.bytes();
+    while let Some(byte) = rest.next() {
+        match byte {
+            b'%' => {
+                let high = (rest.next()? as char).to_digit(16)?;
+                let low = (rest.next()? as char).to_digit(16)?;
+                bytes.push((high * 16 + low) as u8);
+            }
+            b'+' => bytes.push(b' '),
+            byte => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_with_task_and_duration() {
+        assert_eq!(
+            commands("pomodoro://start?task=Write%20report&duration=50m"),
+            Ok(vec![
+                "task Write report".to_string(),
+                "start --for 50m".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_newlines_that_would_add_commands() {
+        for uri in [
+            "pomodoro://start?task=x%0Astop",
+            "pomodoro://start?task=x%0D%0Aupgrade",
+            "pomodoro://start?duration=5m%0Areset",
+        ] {
+            assert!(commands(uri).is_err(), "{} was accepted", uri);
+        }
+    }
+
+    #[test]
+    fn rejects_durations_that_do_not_parse() {
+        assert!(commands("pomodoro://start?duration=soon").is_err());
+        assert!(commands("pomodoro://start?duration=0m").is_err());
+    }
+}
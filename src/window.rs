@@ -0,0 +1,70 @@
+use std::process::Command;
+
+use serde_json::Value;
+
+/// The window that currently has keyboard focus.
+#[derive(Clone, Debug)]
+pub struct FocusedWindow {
+    pub app_id: String, // Wayland app_id, or the X11 class for Xwayland windows
+    pub title: String,
+    pub fullscreen: bool,
+}
+
+impl FocusedWindow {
+    /// Whether the app id or title contains the pattern, ignoring case.
+    pub fn matches(&self, pattern: &str) -> bool {
+        let pattern = pattern.to_lowercase();
+        self.app_id.to_lowercase().contains(&pattern)
+            || self.title.to_lowercase().contains(&pattern)
+    }
+
+    /// A short human-readable label, e.g. for a task.
+    pub fn label(&self) -> String {
+        if self.title.is_empty() {
+            self.app_id.clone()
+        } else {
+            self.title.clone()
+        }
+    }
+}
+
+/// Asks the compositor (sway or Hyprland) which window is focused.
+pub fn focused_window() -> Option<FocusedWindow> {
+    if let Some(tree) = query("swaymsg", &["-t", "get_tree"]) {
+        return find_focused_sway(&tree);
+    }
+    let window = query("hyprctl", &["activewindow", "-j"])?;
+    Some(FocusedWindow {
+        app_id: window["class"].as_str()?.to_string(),
+        title: window["title"].as_str().unwrap_or_default().to_string(),
+        // Older releases report a bool, newer ones the fullscreen mode.
+        fullscreen: window["fullscreen"] == true || window["fullscreen"].as_u64() > Some(0),
+    })
+}
+
+fn query(program: &str, args: &[&str]) -> Option<Value> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+fn find_focused_sway(node: &Value) -> Option<FocusedWindow> {
+    if node["focused"].as_bool() == Some(true) && node["type"] == "con" {
+        let app_id = node["app_id"]
+            .as_str()
+            .or_else(|| node["window_properties"]["class"].as_str())
+            .unwrap_or_default();
+        return Some(FocusedWindow {
+            app_id: app_id.to_string(),
+            title: node["name"].as_str().unwrap_or_default().to_string(),
+            fullscreen: node["fullscreen_mode"].as_u64() > Some(0),
+        });
+    }
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[key].as_array())
+        .flatten()
+        .find_map(find_focused_sway)
+}